@@ -1,3 +1,7 @@
+use crate::datetime;
+use crate::git::GitSource;
+use crate::list::{ListFormat, SortKey};
+use crate::split;
 use getopts::Options;
 use secrecy::SecretString;
 use std::env;
@@ -5,6 +9,67 @@ use std::io::IsTerminal;
 use std::path::PathBuf;
 use std::process;
 
+/// Subcommand to run instead of uploading files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Interactive terminal UI for browsing and managing the files on server.
+    Tui,
+    /// Configuration management.
+    Config(ConfigCommand),
+}
+
+/// Subcommand of `rpaste config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigCommand {
+    /// Creates the configuration file interactively.
+    Init,
+    /// Prints the effective configuration along with the source of each value.
+    Show,
+    /// Checks the configuration file, the token sources and the servers.
+    Validate,
+    /// Prints the path of the configuration file.
+    Path,
+    /// Opens the configuration file in the editor and validates it afterwards.
+    Edit,
+    /// Trusts the project configuration file in the current directory (or its ancestors).
+    Trust,
+}
+
+impl Command {
+    /// Parses the subcommand from the first free arguments and removes them.
+    ///
+    /// Files with the same name as a subcommand can still be uploaded via e.g. `./tui`.
+    fn parse(free: &mut Vec<String>) -> Option<Self> {
+        let (command, len) = match free.first().map(String::as_str) {
+            Some("tui") => (Self::Tui, 1),
+            Some("config") => {
+                let command = match free.get(1).map(String::as_str) {
+                    Some("init") => ConfigCommand::Init,
+                    Some("show") => ConfigCommand::Show,
+                    Some("validate") => ConfigCommand::Validate,
+                    Some("path") => ConfigCommand::Path,
+                    Some("edit") => ConfigCommand::Edit,
+                    Some("trust") => ConfigCommand::Trust,
+                    Some(command) => {
+                        eprintln!("Argument error: `unknown config command: {command}`");
+                        process::exit(1);
+                    }
+                    None => {
+                        eprintln!(
+                            "Argument error: `expected a config command (init, show, validate, path, edit, trust)`"
+                        );
+                        process::exit(1);
+                    }
+                };
+                (Self::Config(command), 2)
+            }
+            _ => return None,
+        };
+        free.drain(..len);
+        Some(command)
+    }
+}
+
 /// Command-line arguments to parse.
 #[derive(Debug, Default)]
 pub struct Args {
@@ -14,10 +79,12 @@ pub struct Args {
     pub server: Option<String>,
     /// Authentication or delete token.
     pub auth: Option<SecretString>,
-    /// URL to shorten.
-    pub url: Option<String>,
-    /// Remote URL to download file.
-    pub remote: Option<String>,
+    /// URLs to shorten.
+    pub urls: Vec<String>,
+    /// Remote URLs to download files.
+    pub remotes: Vec<String>,
+    /// Subcommand to run.
+    pub command: Option<Command>,
     /// Files to upload.
     pub files: Vec<String>,
     /// Whether if the file will disappear after being viewed once.
@@ -34,6 +101,60 @@ pub struct Args {
     pub delete: bool,
     /// Send filename header (give uploaded file a specific name).
     pub filename: Option<String>,
+    /// Patch to generate with `git` and upload.
+    pub git: Option<GitSource>,
+    /// Regular expression for selecting the lines to upload.
+    pub grep: Option<String>,
+    /// Number of context lines to upload around the selected lines.
+    pub context: usize,
+    /// Directory to watch for new or changed files.
+    pub watch: Option<PathBuf>,
+    /// Glob patterns for the files to upload while watching.
+    pub include: Vec<String>,
+    /// File to write the records of the uploads while watching.
+    pub watch_manifest: Option<PathBuf>,
+    /// Manifest file that lists the entries to upload.
+    pub manifest: Option<PathBuf>,
+    /// Maximum size (in bytes) of the parts to split the files into.
+    pub split: Option<u64>,
+    /// Number of parts to upload in parallel.
+    pub jobs: usize,
+    /// URL of the split manifest to download and join the parts of.
+    pub join: Option<String>,
+    /// Template for naming the uploaded files.
+    pub name_template: Option<String>,
+    /// Key to sort the file list by.
+    pub sort: Option<SortKey>,
+    /// Whether if the order of the file list will be reversed.
+    pub reverse: bool,
+    /// Filters (glob patterns or types) for the file list.
+    pub filters: Vec<String>,
+    /// Export format of the file list.
+    pub list_format: Option<ListFormat>,
+    /// Glob patterns for the files to delete.
+    pub delete_match: Vec<String>,
+    /// Type of the files to delete.
+    pub delete_type: Option<String>,
+    /// Minimum age (in seconds) of the files to delete.
+    pub older_than: Option<i64>,
+    /// Whether if all files will be deleted.
+    pub delete_all: bool,
+    /// Whether if the confirmation will be skipped.
+    pub yes: bool,
+    /// Whether if the requests will be printed instead of being sent.
+    pub dry_run: bool,
+    /// Whether if the uploaded files will be fetched and compared with the sent content.
+    pub verify: bool,
+    /// Number of times the verbose flag is given.
+    pub verbose: usize,
+    /// Whether if the files will be picked interactively.
+    pub pick: bool,
+    /// Profiles to upload the files to in addition to the server.
+    pub mirrors: Vec<String>,
+    /// Number of servers that a mirrored upload needs to succeed on.
+    pub quorum: Option<usize>,
+    /// Whether if the content that is already uploaded will be skipped.
+    pub dedupe: Option<bool>,
 }
 
 impl Args {
@@ -47,6 +168,87 @@ impl Args {
         opts.optflag("d", "delete", "delete files from server");
         opts.optflag("o", "oneshot", "generates one shot links");
         opts.optflag("p", "pretty", "prettifies the output");
+        opts.optopt(
+            "",
+            "sort",
+            "sorts the file list (name, size, created, expiry)",
+            "KEY",
+        );
+        opts.optflag("", "reverse", "reverses the order of the file list");
+        opts.optmulti(
+            "",
+            "filter",
+            "filters the file list (glob, file, url, oneshot)",
+            "FILTER",
+        );
+        opts.optopt(
+            "",
+            "format",
+            "sets the format of the file list (csv, tsv, ndjson, json)",
+            "FORMAT",
+        );
+        opts.optmulti(
+            "",
+            "match",
+            "deletes the files matching the pattern",
+            "GLOB",
+        );
+        opts.optopt(
+            "",
+            "type",
+            "deletes the files of the type (file, url, oneshot)",
+            "TYPE",
+        );
+        opts.optopt(
+            "",
+            "older-than",
+            "deletes the files older than the duration",
+            "AGE",
+        );
+        opts.optflag("", "all", "deletes all files");
+        opts.optflag("y", "yes", "skips the confirmation");
+        opts.optflag(
+            "",
+            "dry-run",
+            "prints the requests without sending them (the file list is still retrieved for deleting)",
+        );
+        opts.optflag(
+            "",
+            "dedupe",
+            "skips the content that is already uploaded to the server",
+        );
+        opts.optflag(
+            "",
+            "no-dedupe",
+            "uploads the content even if it is already uploaded",
+        );
+        opts.optflag(
+            "",
+            "verify",
+            "verifies the uploaded files by comparing their SHA-256 checksums",
+        );
+        opts.optflag(
+            "",
+            "pick",
+            "picks the files to upload (or delete) with a fuzzy finder",
+        );
+        opts.optmulti(
+            "",
+            "mirror",
+            "uploads to the profiles as well (comma separated)",
+            "PROFILES",
+        );
+        opts.optopt(
+            "",
+            "quorum",
+            "sets the number of servers a mirrored upload needs to succeed on",
+            "N",
+        );
+        opts.optflagmulti(
+            "",
+            "verbose",
+            "logs the requests to stderr (repeat for more details)",
+        );
         opts.optopt("c", "config", "sets the configuration file", "CONFIG");
         opts.optopt(
             "s",
@@ -60,8 +262,8 @@ impl Args {
             "sets the authentication or delete token",
             "TOKEN",
         );
-        opts.optopt("u", "url", "sets the URL to shorten", "URL");
-        opts.optopt("r", "remote", "sets the remote URL for uploading", "URL");
+        opts.optmulti("u", "url", "sets the URL to shorten", "URL");
+        opts.optmulti("r", "remote", "sets the remote URL for uploading", "URL");
         opts.optopt(
             "e",
             "expire",
@@ -69,6 +271,74 @@ impl Args {
             "TIME",
         );
         opts.optopt("n", "filename", "sets and overrides the filename", "NAME");
+        opts.optopt(
+            "",
+            "name-template",
+            "sets the template for naming the files",
+            "TEMPLATE",
+        );
+        opts.optflagopt(
+            "",
+            "git-diff",
+            "uploads the working tree changes (against REV)",
+            "REV",
+        );
+        opts.optopt("", "git-show", "uploads the given commit(s)", "REV");
+        opts.optflag("", "git-staged", "uploads the staged changes");
+        opts.optopt(
+            "",
+            "grep",
+            "uploads only the lines matching the pattern",
+            "REGEX",
+        );
+        opts.optopt(
+            "",
+            "context",
+            "sets the number of lines to upload around matches",
+            "N",
+        );
+        opts.optopt(
+            "",
+            "watch",
+            "uploads new or changed files in the directory",
+            "DIR",
+        );
+        opts.optmulti(
+            "",
+            "include",
+            "sets the files to upload while watching",
+            "GLOB",
+        );
+        opts.optopt(
+            "",
+            "watch-manifest",
+            "sets the file to record the uploads while watching",
+            "FILE",
+        );
+        opts.optopt(
+            "",
+            "manifest",
+            "uploads the entries listed in the manifest",
+            "FILE",
+        );
+        opts.optopt(
+            "",
+            "split",
+            "uploads the files in parts of the size along with a manifest",
+            "SIZE",
+        );
+        opts.optopt(
+            "",
+            "jobs",
+            "sets the number of parts to upload in parallel",
+            "N",
+        );
+        opts.optopt(
+            "",
+            "join",
+            "downloads the parts listed in the manifest and joins them",
+            "URL",
+        );
 
         let env_args: Vec<String> = env::args().collect();
         let matches = match opts.parse(&env_args[1..]) {
@@ -87,19 +357,30 @@ impl Args {
                 && !matches.opt_present("l")
                 && !matches.opt_present("d")
                 && !matches.opt_present("v")
+                && !matches.opt_present("git-diff")
+                && !matches.opt_present("git-show")
+                && !matches.opt_present("git-staged")
+                && !matches.opt_present("watch")
+                && !matches.opt_present("manifest")
+                && !matches.opt_present("join")
+                && !matches.opt_present("pick")
                 && std::io::stdin().is_terminal())
         {
             let usage = format!(
                 "\n{} {} \u{2014} {}.\
                 \n\u{221F} written by {}\
                 \n\u{221F} licensed under MIT <{}>\
-                \n\nUsage:\n    {} [options] <file(s)>",
+                \n\nUsage:\n    {} [options] <file(s)>\
+                \n    {} [options] tui\
+                \n    {} [options] config <init|show|validate|path|edit|trust>",
                 env!("CARGO_PKG_NAME"),
                 env!("CARGO_PKG_VERSION"),
                 env!("CARGO_PKG_DESCRIPTION"),
                 env!("CARGO_PKG_AUTHORS"),
                 env!("CARGO_PKG_REPOSITORY"),
                 "rpaste",
+                "rpaste",
+                "rpaste",
             );
             println!("{}", opts.usage(&usage));
             process::exit(0)
@@ -110,15 +391,153 @@ impl Args {
             process::exit(0)
         }
 
+        let git_sources = [
+            matches
+                .opt_present("git-diff")
+                .then(|| GitSource::Diff(matches.opt_str("git-diff"))),
+            matches.opt_str("git-show").map(GitSource::Show),
+            matches
+                .opt_present("git-staged")
+                .then_some(GitSource::Staged),
+        ];
+        let mut git_sources = git_sources.into_iter().flatten();
+        let git = git_sources.next();
+        if git_sources.next().is_some() {
+            eprintln!(
+                "Argument error: `only one of --git-diff, --git-show and --git-staged can be used`"
+            );
+            process::exit(1);
+        }
+        if git.is_some() && !matches.free.is_empty() {
+            // `--git-diff main` leaves the revision as a file since the value is optional
+            eprintln!(
+                "Argument error: `files cannot be used with a git source, use --git-diff=REV to diff against a revision`"
+            );
+            process::exit(1);
+        }
+
+        if matches.opt_present("context") && !matches.opt_present("grep") {
+            eprintln!("Argument error: `--context requires --grep`");
+            process::exit(1);
+        }
+        let context = match matches.opt_str("context").map(|v| v.parse()) {
+            Some(Ok(context)) => context,
+            Some(Err(e)) => {
+                eprintln!("Argument error: `invalid context: {e}`");
+                process::exit(1);
+            }
+            None => 0,
+        };
+
+        let sort = match matches.opt_str("sort").map(|v| v.parse()) {
+            Some(Ok(sort)) => Some(sort),
+            Some(Err(e)) => {
+                eprintln!("Argument error: `{e}`");
+                process::exit(1);
+            }
+            None => None,
+        };
+
+        let list_format = match matches.opt_str("format").map(|v| v.parse()) {
+            Some(Ok(format)) => Some(format),
+            Some(Err(e)) => {
+                eprintln!("Argument error: `{e}`");
+                process::exit(1);
+            }
+            None => None,
+        };
+
+        let mut files = matches.free.clone();
+        let command = Command::parse(&mut files);
+        if command.is_some() && !files.is_empty() {
+            eprintln!(
+                "Argument error: `unexpected arguments: {}`",
+                files.join(" ")
+            );
+            process::exit(1);
+        }
+        if command == Some(Command::Tui) && matches.opt_present("dry-run") {
+            eprintln!("Argument error: `--dry-run cannot be used with the tui command`");
+            process::exit(1);
+        }
+        if matches.opt_present("watch") && matches.opt_present("dry-run") {
+            eprintln!("Argument error: `--dry-run cannot be used with --watch`");
+            process::exit(1);
+        }
+
+        let quorum = match matches.opt_str("quorum").map(|v| v.parse()) {
+            Some(Ok(quorum)) => Some(quorum),
+            Some(Err(e)) => {
+                eprintln!("Argument error: `invalid quorum: {e}`");
+                process::exit(1);
+            }
+            None => None,
+        };
+
+        let split = match matches.opt_str("split") {
+            Some(size) => match split::parse_size(&size) {
+                Some(size) => Some(size),
+                None => {
+                    eprintln!("Argument error: `invalid size: {size}`");
+                    process::exit(1);
+                }
+            },
+            None => None,
+        };
+        if split.is_some() && matches.free.is_empty() {
+            eprintln!("Argument error: `--split requires files`");
+            process::exit(1);
+        }
+        if matches.opt_present("jobs") && split.is_none() {
+            eprintln!("Argument error: `--jobs requires --split`");
+            process::exit(1);
+        }
+        let jobs = match matches.opt_str("jobs").map(|v| v.parse()) {
+            Some(Ok(jobs)) if jobs > 0 => jobs,
+            Some(Ok(_)) => {
+                eprintln!("Argument error: `invalid jobs: must be at least 1`");
+                process::exit(1);
+            }
+            Some(Err(e)) => {
+                eprintln!("Argument error: `invalid jobs: {e}`");
+                process::exit(1);
+            }
+            None => 1,
+        };
+
+        let dedupe = match (
+            matches.opt_present("dedupe"),
+            matches.opt_present("no-dedupe"),
+        ) {
+            (true, true) => {
+                eprintln!("Argument error: `--dedupe and --no-dedupe cannot be used together`");
+                process::exit(1);
+            }
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            (false, false) => None,
+        };
+
+        let older_than = match matches.opt_str("older-than") {
+            Some(age) => match datetime::parse_duration(&age) {
+                Some(age) => Some(age),
+                None => {
+                    eprintln!("Argument error: `invalid duration: {age}`");
+                    process::exit(1);
+                }
+            },
+            None => None,
+        };
+
         Args {
-            config: env::var("RPASTE_CONFIG")
-                .ok()
-                .or_else(|| matches.opt_str("c"))
+            config: matches
+                .opt_str("c")
+                .or_else(|| env::var("RPASTE_CONFIG").ok())
                 .map(PathBuf::from),
             server: matches.opt_str("s"),
             auth: matches.opt_str("a").map(Into::into),
-            url: matches.opt_str("u"),
-            remote: matches.opt_str("r"),
+            urls: matches.opt_strs("u"),
+            remotes: matches.opt_strs("r"),
             oneshot: matches.opt_present("o"),
             expire: matches.opt_str("e"),
             prettify: matches.opt_present("p"),
@@ -126,7 +545,41 @@ impl Args {
             list_files: matches.opt_present("l"),
             delete: matches.opt_present("d"),
             filename: matches.opt_str("n"),
-            files: matches.free,
+            git,
+            grep: matches.opt_str("grep"),
+            context,
+            watch: matches.opt_str("watch").map(PathBuf::from),
+            include: matches.opt_strs("include"),
+            watch_manifest: matches.opt_str("watch-manifest").map(PathBuf::from),
+            manifest: matches.opt_str("manifest").map(PathBuf::from),
+            split,
+            jobs,
+            join: matches.opt_str("join"),
+            name_template: matches.opt_str("name-template"),
+            sort,
+            reverse: matches.opt_present("reverse"),
+            filters: matches.opt_strs("filter"),
+            list_format,
+            delete_match: matches.opt_strs("match"),
+            delete_type: matches.opt_str("type"),
+            older_than,
+            delete_all: matches.opt_present("all"),
+            yes: matches.opt_present("y"),
+            dry_run: matches.opt_present("dry-run"),
+            verify: matches.opt_present("verify"),
+            verbose: matches.opt_count("verbose"),
+            pick: matches.opt_present("pick"),
+            mirrors: matches
+                .opt_strs("mirror")
+                .iter()
+                .flat_map(|profiles| profiles.split(','))
+                .map(|profile| profile.trim().to_string())
+                .filter(|profile| !profile.is_empty())
+                .collect(),
+            quorum,
+            dedupe,
+            command,
+            files,
         }
     }
 }
@@ -0,0 +1,90 @@
+use crate::error::Result;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+
+/// Returns the hex encoded SHA-256 digest of the given content.
+pub fn hash<R: Read>(mut reader: R) -> Result<String> {
+    let mut hasher = Hasher::default();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Incremental SHA-256 hasher, which is also a writer of the data to hash.
+#[derive(Debug, Default, Clone)]
+pub struct Hasher(Sha256);
+
+impl Hasher {
+    /// Updates the digest with the given bytes.
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    /// Returns the hex encoded digest of the data.
+    pub fn finish(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reader that computes the SHA-256 digest of the data that is read.
+#[derive(Debug)]
+pub struct HashingReader<R: Read> {
+    /// Inner reader.
+    inner: R,
+    /// Hasher of the data.
+    hasher: Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Constructs a new instance.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Hasher::default(),
+        }
+    }
+
+    /// Returns the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the hex encoded digest of the data that is read.
+    pub fn finish(self) -> String {
+        self.hasher.finish()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_content() -> Result<()> {
+        let digest = "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73";
+        assert_eq!(digest, hash("content".as_bytes())?);
+
+        let mut reader = HashingReader::new("content".as_bytes());
+        io::copy(&mut reader, &mut io::sink())?;
+        assert_eq!(digest, reader.finish());
+        Ok(())
+    }
+}
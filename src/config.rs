@@ -1,24 +1,52 @@
 use crate::args::Args;
+use crate::error::{Error, Result};
+use crate::project::{self, TrustStore};
+use percent_encoding::percent_decode_str;
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use url::Url;
+
+/// Service name of the OS keyring entries.
+pub const KEYRING_SERVICE: &str = "rustypaste";
 
 /// Configuration values.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     /// Server configuration.
+    #[serde(default)]
     pub server: ServerConfig,
     /// Paste configuration.
+    #[serde(default)]
     pub paste: PasteConfig,
     /// Style configuration.
     pub style: Option<StyleConfig>,
+    /// Named server configurations that can be used instead of [`Config::server`].
+    #[serde(default)]
+    pub profiles: HashMap<String, ServerConfig>,
+    /// Whether if the requests will be printed instead of being sent.
+    #[serde(skip)]
+    pub dry_run: bool,
+    /// Whether if the uploaded files will be fetched and compared with the sent content.
+    #[serde(skip)]
+    pub verify: bool,
 }
 
 /// Server configuration.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServerConfig {
     /// Server address.
+    #[serde(default)]
     pub address: String,
+    /// Addresses of the servers to fail over to (in order) if the server is unavailable.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Public URL of the server, used in the URLs shown to the users instead of the server
+    /// address (e.g. if the server is uploaded to via an internal address).
+    pub public_url: Option<String>,
     /// Token for authentication, omitted when serializing the configuration.
     #[serde(skip_serializing)]
     pub auth_token: Option<SecretString>,
@@ -26,6 +54,14 @@ pub struct ServerConfig {
     ///
     /// Leading and trailing whitespace will be trimmed.
     pub auth_token_file: Option<String>,
+    /// A command that prints the token for authentication, e.g. `pass show rustypaste`.
+    ///
+    /// Leading and trailing whitespace will be trimmed.
+    pub auth_token_command: Option<String>,
+    /// Name of the OS keyring entry that stores the token for authentication.
+    ///
+    /// Requires the `keyring` feature.
+    pub auth_token_keyring: Option<String>,
     /// Token for deleting files, omitted when serializing the configuration.
     #[serde(skip_serializing)]
     pub delete_token: Option<SecretString>,
@@ -33,6 +69,24 @@ pub struct ServerConfig {
     ///
     /// Leading and trailing whitespace will be trimmed.
     pub delete_token_file: Option<String>,
+    /// A command that prints the token for deleting files.
+    ///
+    /// Leading and trailing whitespace will be trimmed.
+    pub delete_token_command: Option<String>,
+    /// Name of the OS keyring entry that stores the token for deleting files.
+    ///
+    /// Requires the `keyring` feature.
+    pub delete_token_keyring: Option<String>,
+    /// Whether the auth token is given via the environment or the arguments, which take
+    /// precedence over its sources.
+    #[serde(skip)]
+    pub(crate) auth_token_given: bool,
+    /// Whether the delete token is given via the environment or the arguments.
+    #[serde(skip)]
+    pub(crate) delete_token_given: bool,
+    /// Whether the token sources are read, see [`ServerConfig::parse_token_files`].
+    #[serde(skip)]
+    pub(crate) tokens_read: bool,
 }
 
 /// Paste configuration.
@@ -45,6 +99,13 @@ pub struct PasteConfig {
     /// Filename.
     #[serde(skip_deserializing)]
     pub filename: Option<String>,
+    /// Profiles to upload the files to in addition to the server.
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Number of servers that a mirrored upload needs to succeed on (defaults to all).
+    pub quorum: Option<usize>,
+    /// Whether if the content that is already uploaded (and not expired) will be skipped.
+    pub dedupe: Option<bool>,
 }
 
 /// Style configuration.
@@ -54,16 +115,219 @@ pub struct StyleConfig {
     pub prettify: bool,
 }
 
+/// Configuration file to merge into the configuration.
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Parsed contents of the file.
+    pub table: toml::Table,
+}
+
+impl ConfigLayer {
+    /// Reads the configuration file at the given path.
+    pub fn read(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            table: toml::from_str(&fs::read_to_string(path)?)?,
+        })
+    }
+
+    /// Adds the layer to the given layers, preceded by the files in its `include` array.
+    ///
+    /// The included files are resolved relative to the including file and can include other
+    /// files as well. `stack` contains the files that are being included for detecting cycles.
+    pub fn expand(mut self, layers: &mut Vec<Self>, stack: &mut Vec<PathBuf>) -> Result<()> {
+        let includes = match self.table.remove("include") {
+            Some(toml::Value::Array(includes)) => includes,
+            Some(_) => {
+                return Err(Error::ConfigError(format!(
+                    "include must be an array of paths in {}",
+                    self.path.display()
+                )))
+            }
+            None => Vec::new(),
+        };
+        stack.push(fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone()));
+        for include in includes {
+            let include = include.as_str().ok_or_else(|| {
+                Error::ConfigError(format!(
+                    "include must be an array of paths in {}",
+                    self.path.display()
+                ))
+            })?;
+            let path = self
+                .path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(shellexpand::tilde(include).as_ref());
+            if stack.contains(&fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+                return Err(Error::ConfigError(format!(
+                    "{} is included recursively",
+                    path.display()
+                )));
+            }
+            log::info!("Including the configuration file: {path:?}");
+            Self::read(&path)?.expand(layers, stack)?;
+        }
+        stack.pop();
+        layers.push(self);
+        Ok(())
+    }
+}
+
+/// Configuration files to use.
+#[derive(Debug)]
+pub struct ConfigFiles {
+    /// System-wide configuration file, if exists.
+    pub system: Option<PathBuf>,
+    /// User configuration file, if found.
+    pub user: Option<PathBuf>,
+    /// Path to create the user configuration file at.
+    pub default: PathBuf,
+    /// Project configuration file, if found.
+    pub project: Option<PathBuf>,
+    /// Configuration file given via the arguments (or the environment).
+    pub custom: Option<PathBuf>,
+    /// Trusted project configuration files.
+    pub trust_store: TrustStore,
+}
+
+impl ConfigFiles {
+    /// Returns the configuration file to create or edit, i.e. the given or the user file.
+    pub fn target(&self) -> &Path {
+        self.custom
+            .as_deref()
+            .or(self.user.as_deref())
+            .unwrap_or(&self.default)
+    }
+
+    /// Reads the configuration files (along with their includes), lowest precedence first.
+    ///
+    /// The files are merged in the following order: system, user, project and the given file.
+    /// The user is asked to trust the project configuration if needed, see [`project::load`].
+    pub fn layers(&self) -> Result<Vec<ConfigLayer>> {
+        let mut layers = Vec::new();
+        for path in [&self.system, &self.user].into_iter().flatten() {
+            log::info!("Using the configuration file: {path:?}");
+            ConfigLayer::read(path)?.expand(&mut layers, &mut Vec::new())?;
+        }
+        if let Some(ref path) = self.project {
+            log::info!("Using the project configuration file: {path:?}");
+            layers.extend(project::load(path, &self.trust_store)?);
+        }
+        if let Some(ref path) = self.custom {
+            log::info!("Using the configuration file: {path:?}");
+            ConfigLayer::read(path)?.expand(&mut layers, &mut Vec::new())?;
+        }
+        Ok(layers)
+    }
+}
+
+/// Merges the `overlay` table into the `base` table.
+///
+/// Nested tables are merged key by key while the other values (including arrays) are replaced.
+pub fn merge_tables(base: &mut toml::Table, overlay: &toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay)
+            }
+            _ => {
+                base.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+}
+
 impl Config {
+    /// Merges the configuration layers (lowest precedence first) and parses the result.
+    pub fn from_layers(layers: &[ConfigLayer]) -> Result<Self> {
+        let mut table = toml::Table::new();
+        for layer in layers {
+            merge_tables(&mut table, &layer.table);
+        }
+        Ok(table.try_into()?)
+    }
+
+    /// Override the configuration file with environment variables.
+    ///
+    /// `var` returns the value of the given variable, empty values are ignored. The variables
+    /// take precedence over the configuration file (including the token sources) and are
+    /// overridden by the arguments (see [`Config::update_from_args`]).
+    pub fn update_from_env<F: Fn(&str) -> Option<String>>(&mut self, var: F) {
+        let var = |name: &str| {
+            var(name).filter(|value| !value.is_empty()).inspect(|_| {
+                log::info!("Using {name} from the environment");
+            })
+        };
+        let flag = |name: &str| {
+            var(name).and_then(|value| match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Some(true),
+                "0" | "false" | "no" | "off" => Some(false),
+                _ => {
+                    eprintln!("Warning: ignoring invalid value of {name}: {value}");
+                    None
+                }
+            })
+        };
+        if let Some(address) = var("RPASTE_SERVER") {
+            self.server.address = address;
+            self.server.addresses.clear();
+        }
+        let mut errors = Vec::new();
+        if let Some(path) = var("RPASTE_AUTH_TOKEN_FILE") {
+            if let Some(token) =
+                self.server
+                    .read_token("auth", Some(&path), None, None, &mut errors)
+            {
+                self.server.auth_token = Some(token);
+                self.server.auth_token_given = true;
+            }
+        }
+        if let Some(token) = var("RPASTE_AUTH_TOKEN") {
+            self.server.auth_token = Some(token.into());
+            self.server.auth_token_given = true;
+        }
+        if let Some(path) = var("RPASTE_DELETE_TOKEN_FILE") {
+            if let Some(token) =
+                self.server
+                    .read_token("delete", Some(&path), None, None, &mut errors)
+            {
+                self.server.delete_token = Some(token);
+                self.server.delete_token_given = true;
+            }
+        }
+        errors.iter().for_each(|e| eprintln!("{e}"));
+        if let Some(token) = var("RPASTE_DELETE_TOKEN") {
+            self.server.delete_token = Some(token.into());
+            self.server.delete_token_given = true;
+        }
+        if let Some(oneshot) = flag("RPASTE_ONESHOT") {
+            self.paste.oneshot = Some(oneshot);
+        }
+        if let Some(expire) = var("RPASTE_EXPIRE") {
+            self.paste.expire = Some(expire);
+        }
+        if let Some(prettify) = flag("RPASTE_PRETTIFY") {
+            self.style = Some(StyleConfig { prettify });
+        }
+    }
+
     /// Override the configuration file with arguments.
     pub fn update_from_args(&mut self, args: &Args) {
         if let Some(server_address) = &args.server {
             self.server.address = server_address.to_string();
+            self.server.addresses.clear();
         }
         if args.auth.is_some() {
+            log::info!("Using the auth token from the command line");
             self.server.auth_token = args.auth.as_ref().cloned();
+            self.server.auth_token_given = true;
             if args.delete {
+                log::info!("Using the delete token from the command line");
                 self.server.delete_token = args.auth.as_ref().cloned();
+                self.server.delete_token_given = true;
             }
         }
         if args.oneshot {
@@ -75,30 +339,293 @@ impl Config {
         if args.filename.is_some() {
             self.paste.filename = args.filename.as_ref().cloned();
         }
+        if !args.mirrors.is_empty() {
+            self.paste.mirrors = args.mirrors.clone();
+        }
+        if args.quorum.is_some() {
+            self.paste.quorum = args.quorum;
+        }
+        if args.dedupe.is_some() {
+            self.paste.dedupe = args.dedupe;
+        }
+        if args.dry_run {
+            self.dry_run = true;
+        }
+        if args.verify {
+            self.verify = true;
+        }
     }
 
-    /// Parses the files referenced by [Config::auth_token_file] and [Config::delete_token_file].
+    /// Parses the token files of the server configuration and the profiles.
     ///
-    /// Updates the respective token variables with the contents of the files.
+    /// See [`ServerConfig::parse_token_files`].
     pub fn parse_token_files(&mut self) {
-        if let Some(path) = &self.server.auth_token_file {
-            let path = shellexpand::tilde(path).to_string();
-            match fs::read_to_string(path) {
-                Ok(token) => self.server.auth_token = Some(token.trim().into()),
-                Err(e) => eprintln!("Error while reading token file: {e}"),
-            };
+        self.server.parse_token_files();
+        self.profiles
+            .values_mut()
+            .for_each(ServerConfig::parse_token_files);
+    }
+
+    /// Returns the configuration with the server of the given profile.
+    ///
+    /// The tokens of the profile are read from their sources when it is used for the first
+    /// time, see [`ServerConfig::parse_token_files`].
+    pub fn with_profile(&mut self, name: &str) -> Result<Self> {
+        let server = self
+            .profiles
+            .get_mut(name)
+            .ok_or_else(|| Error::UnknownProfileError(name.to_string()))?;
+        server.parse_token_files();
+        Ok(Self {
+            server: server.clone(),
+            ..self.clone()
+        })
+    }
+
+    /// Returns the configuration of the server that the given paste URL belongs to, along with
+    /// the name of the file in the URL.
+    ///
+    /// The URL is matched against the server and the profiles (in the order of their names).
+    /// Returns `None` if the given file is not an HTTP(S) URL. The profile is used as in
+    /// [`Config::with_profile`].
+    pub fn locate_paste(&mut self, file: &str) -> Result<Option<(Self, String)>> {
+        let url = match Url::parse(file) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            _ => return Ok(None),
         };
+        if let Some(file_name) = self.server.file_name(&url)? {
+            return Ok(Some((self.clone(), file_name)));
+        }
+        let mut profiles = self.profiles.keys().cloned().collect::<Vec<_>>();
+        profiles.sort();
+        for name in profiles {
+            if let Some(file_name) = self.profiles[&name].file_name(&url)? {
+                return Ok(Some((self.with_profile(&name)?, file_name)));
+            }
+        }
+        Err(Error::DeleteError(format!(
+            "{file} does not belong to any of the configured servers"
+        )))
+    }
+}
 
-        if let Some(path) = &self.server.delete_token_file {
+impl ServerConfig {
+    /// Rewrites the URL that starts with the server address to start with the public URL.
+    pub fn to_public_url(&self, url: &str) -> String {
+        match self.public_url {
+            Some(ref public_url) => rewrite_url(url, &self.address, public_url),
+            None => url.to_string(),
+        }
+    }
+
+    /// Returns the (decoded) name of the file in the given URL if the URL belongs to the server.
+    ///
+    /// The URL belongs to the server if it starts with one of the
+    /// [`candidates`](ServerConfig::candidates) or the public URL. The oneshot files and URLs
+    /// are served under the same path as the other files.
+    pub fn file_name(&self, url: &Url) -> Result<Option<String>> {
+        let origin = |url: &Url| {
+            (
+                url.scheme().to_string(),
+                url.host_str().map(String::from),
+                url.port_or_known_default(),
+            )
+        };
+        for base in self
+            .candidates()
+            .into_iter()
+            .chain(self.public_url.as_deref())
+        {
+            let Ok(base) = Url::parse(base) else { continue };
+            if origin(&base) != origin(url) {
+                continue;
+            }
+            let Some(path) = url.path().strip_prefix(base.path().trim_end_matches('/')) else {
+                continue;
+            };
+            let Some(name) = path.strip_prefix('/') else {
+                continue;
+            };
+            if name.is_empty() || name.contains('/') {
+                return Err(Error::DeleteError(format!(
+                    "{url} does not contain a file name"
+                )));
+            }
+            return Ok(Some(
+                percent_decode_str(name).decode_utf8_lossy().to_string(),
+            ));
+        }
+        Ok(None)
+    }
+
+    /// Returns the server address followed by the failover addresses, without duplicates.
+    pub fn candidates(&self) -> Vec<&str> {
+        let mut candidates = Vec::new();
+        for address in [&self.address].into_iter().chain(&self.addresses) {
+            if !address.is_empty() && !candidates.contains(&address.as_str()) {
+                candidates.push(address.as_str());
+            }
+        }
+        candidates
+    }
+
+    /// Reads the tokens from the files, commands and keyring entries that are configured via
+    /// [ServerConfig::auth_token_file], [ServerConfig::auth_token_command],
+    /// [ServerConfig::auth_token_keyring] and their delete token counterparts.
+    ///
+    /// Updates the respective token variables with the first token that can be read, in the
+    /// given order of the sources. Errors are printed, see [`ServerConfig::read_tokens`] for
+    /// collecting them instead.
+    ///
+    /// The sources are only read once, and not at all for the tokens that are given via the
+    /// environment or the arguments.
+    pub fn parse_token_files(&mut self) {
+        if self.tokens_read {
+            return;
+        }
+        self.read_tokens().iter().for_each(|e| eprintln!("{e}"));
+    }
+
+    /// Reads the tokens as [`ServerConfig::parse_token_files`] and returns the errors.
+    pub fn read_tokens(&mut self) -> Vec<Error> {
+        self.tokens_read = true;
+        let mut errors = Vec::new();
+        if self.auth_token.is_some() {
+            log::info!(
+                "Found the auth token for {} in the configuration",
+                self.address
+            );
+        }
+        if self.delete_token.is_some() {
+            log::info!(
+                "Found the delete token for {} in the configuration",
+                self.address
+            );
+        }
+        if self.auth_token_given {
+            log::info!("Skipping the auth token sources for {}", self.address);
+        } else if let Some(token) = self.read_token(
+            "auth",
+            self.auth_token_file.as_deref(),
+            self.auth_token_command.as_deref(),
+            self.auth_token_keyring.as_deref(),
+            &mut errors,
+        ) {
+            self.auth_token = Some(token);
+        }
+        if self.delete_token_given {
+            log::info!("Skipping the delete token sources for {}", self.address);
+        } else if let Some(token) = self.read_token(
+            "delete",
+            self.delete_token_file.as_deref(),
+            self.delete_token_command.as_deref(),
+            self.delete_token_keyring.as_deref(),
+            &mut errors,
+        ) {
+            self.delete_token = Some(token);
+        }
+        errors
+    }
+
+    /// Reads the token of the given kind from the first source that succeeds.
+    ///
+    /// The errors of the failed sources are added to `errors`.
+    fn read_token(
+        &self,
+        kind: &str,
+        file: Option<&str>,
+        command: Option<&str>,
+        keyring: Option<&str>,
+        errors: &mut Vec<Error>,
+    ) -> Option<SecretString> {
+        if let Some(path) = file {
             let path = shellexpand::tilde(path).to_string();
-            match fs::read_to_string(path) {
-                Ok(token) => self.server.delete_token = Some(token.trim().into()),
-                Err(e) => eprintln!("Error while reading token file: {e}"),
+            log::info!("Reading the {kind} token for {} from {path}", self.address);
+            match fs::read_to_string(&path) {
+                Ok(token) => return Some(token.trim().into()),
+                Err(e) => errors.push(Error::TokenError(format!(
+                    "cannot read the token file {path}: {e}"
+                ))),
             };
-        };
+        }
+        if let Some(command) = command {
+            log::info!(
+                "Reading the {kind} token for {} from the command: {command}",
+                self.address
+            );
+            match run_token_command(command) {
+                Ok(token) => return Some(token.into()),
+                Err(e) => errors.push(e),
+            }
+        }
+        if let Some(name) = keyring {
+            log::info!(
+                "Reading the {kind} token for {} from the keyring entry: {name}",
+                self.address
+            );
+            match read_keyring_token(name) {
+                Ok(token) => return Some(token.into()),
+                Err(e) => errors.push(e),
+            }
+        }
+        None
+    }
+}
+
+/// Replaces the `from` prefix of the URL with `to`, ignoring the trailing slashes of them.
+fn rewrite_url(url: &str, from: &str, to: &str) -> String {
+    let (from, to) = (from.trim_end_matches('/'), to.trim_end_matches('/'));
+    match url.strip_prefix(from) {
+        Some(path) if !from.is_empty() && (path.is_empty() || path.starts_with('/')) => {
+            format!("{to}{path}")
+        }
+        _ => url.to_string(),
     }
 }
 
+/// Runs the given command via the shell and returns the token it prints.
+///
+/// The command does not inherit stdin since it might be the data to upload.
+fn run_token_command(command: &str) -> Result<String> {
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+    let output = Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| Error::TokenError(format!("cannot run {command}: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::TokenError(format!(
+            "{command} failed with {}",
+            output.status
+        )));
+    }
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if token.is_empty() {
+        return Err(Error::TokenError(format!("{command} printed no token")));
+    }
+    Ok(token)
+}
+
+/// Returns the token stored in the OS keyring entry with the given name.
+#[cfg(feature = "keyring")]
+fn read_keyring_token(name: &str) -> Result<String> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .and_then(|entry| entry.get_password())
+        .map_err(|e| Error::TokenError(format!("cannot read the keyring entry {name}: {e}")))
+}
+
+/// Returns an error since the keyring support is not enabled.
+#[cfg(not(feature = "keyring"))]
+fn read_keyring_token(name: &str) -> Result<String> {
+    Err(Error::TokenError(format!(
+        "cannot read the keyring entry {name}: rpaste is built without the keyring feature"
+    )))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,6 +655,58 @@ mod tests {
         assert!(debug.contains("[REDACTED]"));
     }
 
+    #[test]
+    /// Test that the environment variables override the configuration file and are overridden
+    /// by the arguments.
+    fn test_env_precedence() {
+        let mut cfg: Config = toml::from_str(
+            r#"
+                [server]
+                address = "https://config.example.com"
+                auth_token = "config-token"
+                delete_token = "config-delete-token"
+
+                [paste]
+                oneshot = true
+                expire = "1d"
+
+                [style]
+                prettify = true
+            "#,
+        )
+        .expect("config should deserialize");
+        let env = HashMap::from([
+            ("RPASTE_SERVER", "https://env.example.com"),
+            ("RPASTE_AUTH_TOKEN", "env-token"),
+            ("RPASTE_DELETE_TOKEN", ""),
+            ("RPASTE_ONESHOT", "false"),
+            ("RPASTE_EXPIRE", "1h"),
+            ("RPASTE_PRETTIFY", "maybe"),
+        ]);
+        cfg.update_from_env(|name| env.get(name).map(|value| value.to_string()));
+        assert_eq!("https://env.example.com", cfg.server.address);
+        assert_eq!(
+            Some("env-token"),
+            cfg.server.auth_token.as_ref().map(|t| t.expose_secret())
+        );
+        assert_eq!(
+            Some("config-delete-token"),
+            cfg.server.delete_token.as_ref().map(|t| t.expose_secret())
+        );
+        assert_eq!(Some(false), cfg.paste.oneshot);
+        assert_eq!(Some(true), cfg.style.as_ref().map(|style| style.prettify));
+
+        let args = Args {
+            server: Some(String::from("https://args.example.com")),
+            oneshot: true,
+            ..Args::default()
+        };
+        cfg.update_from_args(&args);
+        assert_eq!("https://args.example.com", cfg.server.address);
+        assert_eq!(Some(true), cfg.paste.oneshot);
+        assert_eq!(Some("1h"), cfg.paste.expire.as_deref());
+    }
+
     #[test]
     /// Test that the token file is being properly processed.
     fn test_parse_token_files_no_whitespace() {
@@ -162,4 +741,202 @@ mod tests {
             Some(token.as_str())
         );
     }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test that the token is read from the output of the command.
+    fn test_token_command() {
+        let mut cfg = Config::default();
+        cfg.server.delete_token_command = Some(String::from("echo '  delete-secret '"));
+        cfg.parse_token_files();
+        assert_eq!(
+            cfg.server
+                .delete_token
+                .as_ref()
+                .map(|token| token.expose_secret()),
+            Some("delete-secret")
+        );
+
+        assert!(matches!(
+            run_token_command("exit 3"),
+            Err(Error::TokenError(message)) if message.contains("exit status: 3")
+        ));
+        assert!(run_token_command("true").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// Test that the token sources are only read for the profiles in use and not for the
+    /// tokens given via the environment.
+    fn test_read_token_sources_lazily() -> Result<()> {
+        let mut cfg = Config::default();
+        cfg.server.auth_token_command = Some(String::from("echo command-secret"));
+        cfg.server.delete_token_command = Some(String::from("echo delete-secret"));
+        cfg.profiles.insert(
+            String::from("work"),
+            ServerConfig {
+                auth_token_command: Some(String::from("echo work-secret")),
+                ..ServerConfig::default()
+            },
+        );
+        cfg.update_from_env(|name| {
+            (name == "RPASTE_AUTH_TOKEN").then(|| String::from("env-secret"))
+        });
+        cfg.server.parse_token_files();
+        let token = |server: &ServerConfig| {
+            server
+                .auth_token
+                .as_ref()
+                .map(|token| token.expose_secret().to_string())
+        };
+        assert_eq!(Some("env-secret"), token(&cfg.server).as_deref());
+        assert_eq!(
+            Some("delete-secret"),
+            cfg.server
+                .delete_token
+                .as_ref()
+                .map(|token| token.expose_secret())
+        );
+        assert!(!cfg.profiles["work"].tokens_read);
+
+        let work = cfg.with_profile("work")?;
+        assert_eq!(Some("work-secret"), token(&work.server).as_deref());
+        assert!(cfg.profiles["work"].tokens_read);
+        Ok(())
+    }
+
+    #[test]
+    /// Test that the URLs are rewritten between the server address and the public URL.
+    fn test_public_url() {
+        let server = ServerConfig {
+            address: String::from("http://paste.internal:8000"),
+            public_url: Some(String::from("https://paste.example.com/")),
+            ..ServerConfig::default()
+        };
+        assert_eq!(
+            "https://paste.example.com/file.txt\n",
+            server.to_public_url("http://paste.internal:8000/file.txt\n")
+        );
+        assert_eq!(
+            "http://paste.internal:80001/file.txt",
+            server.to_public_url("http://paste.internal:80001/file.txt")
+        );
+    }
+
+    #[test]
+    /// Test that the paste URLs are matched against the server and the profiles.
+    fn test_locate_paste() -> Result<()> {
+        let mut config = Config::default();
+        config.server.address = String::from("http://paste.internal:8000/");
+        config.server.public_url = Some(String::from("https://paste.example.com"));
+        config.profiles.insert(
+            String::from("work"),
+            ServerConfig {
+                address: String::from("https://work.example.com/paste"),
+                ..ServerConfig::default()
+            },
+        );
+        let mut locate = |file: &str| {
+            config
+                .locate_paste(file)
+                .map(|v| v.map(|(config, file_name)| (config.server.address, file_name)))
+        };
+        assert_eq!(None, locate("file.txt")?);
+        assert_eq!(
+            Some((
+                String::from("http://paste.internal:8000/"),
+                String::from("my file.txt")
+            )),
+            locate("https://paste.example.com/my%20file.txt")?
+        );
+        assert_eq!(
+            Some((
+                String::from("https://work.example.com/paste"),
+                String::from("abc")
+            )),
+            locate("https://work.example.com:443/paste/abc?download#top")?
+        );
+        assert!(locate("https://work.example.com/abc").is_err());
+        assert!(locate("https://paste.example.com/").is_err());
+        assert!(locate("https://other.example.com/file.txt").is_err());
+        Ok(())
+    }
+
+    #[test]
+    /// Test that the layers are merged field by field.
+    fn test_merge_layers() -> Result<()> {
+        let layer = |contents: &str| -> Result<ConfigLayer> {
+            Ok(ConfigLayer {
+                path: PathBuf::new(),
+                table: toml::from_str(contents)?,
+            })
+        };
+        let cfg = Config::from_layers(&[
+            layer(
+                r#"
+                [server]
+                address = "https://paste.example.com"
+                auth_token = "user-secret"
+                [paste]
+                oneshot = true
+                "#,
+            )?,
+            layer(
+                r#"
+                [server]
+                address = "https://team.example.com"
+                [paste]
+                expire = "1h"
+                "#,
+            )?,
+        ])?;
+        assert_eq!("https://team.example.com", cfg.server.address);
+        assert_eq!(
+            Some("user-secret"),
+            cfg.server
+                .auth_token
+                .as_ref()
+                .map(|token| token.expose_secret())
+        );
+        assert_eq!(Some(true), cfg.paste.oneshot);
+        assert_eq!(Some("1h"), cfg.paste.expire.as_deref());
+        Ok(())
+    }
+
+    #[test]
+    /// Test that the included files are merged before the including file.
+    fn test_includes() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rpaste-include-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("shared"))?;
+        fs::write(
+            dir.join("shared").join("base.toml"),
+            "[server]\naddress = \"https://base.example.com\"\n[paste]\nexpire = \"1h\"\n",
+        )?;
+        fs::write(
+            dir.join("config.toml"),
+            "include = [\"shared/base.toml\"]\n[paste]\nexpire = \"7d\"\n",
+        )?;
+        fs::write(dir.join("a.toml"), "include = [\"b.toml\"]\n")?;
+        fs::write(dir.join("b.toml"), "include = [\"a.toml\"]\n")?;
+        let expand = |name: &str| -> Result<Vec<ConfigLayer>> {
+            let mut layers = Vec::new();
+            ConfigLayer::read(&dir.join(name))?.expand(&mut layers, &mut Vec::new())?;
+            Ok(layers)
+        };
+        let layers = expand("config.toml");
+        let recursive = expand("a.toml");
+        fs::remove_dir_all(&dir)?;
+
+        let layers = layers?;
+        assert_eq!(2, layers.len());
+        assert!(layers[0].path.ends_with("shared/base.toml"));
+        let cfg = Config::from_layers(&layers)?;
+        assert_eq!("https://base.example.com", cfg.server.address);
+        assert_eq!(Some("7d"), cfg.paste.expire.as_deref());
+        assert!(matches!(
+            recursive,
+            Err(Error::ConfigError(message)) if message.ends_with("is included recursively")
+        ));
+        Ok(())
+    }
 }
@@ -0,0 +1,463 @@
+use crate::args::{Args, ConfigCommand};
+use crate::config::{Config, ConfigFiles, ServerConfig};
+use crate::error::{Error, Result};
+use crate::project::{self, PROJECT_CONFIG_FILE};
+use crate::prompt;
+use crate::upload::Uploader;
+use colored::Colorize;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::Command;
+use url::Url;
+
+/// Value of a configuration key along with the source that set it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Setting {
+    /// Key of the setting, e.g. `server.address`.
+    key: String,
+    /// Printable value (with the secrets redacted), `None` if not set.
+    value: Option<String>,
+    /// Source of the value, e.g. the path of a configuration file.
+    source: String,
+}
+
+/// Runs the configuration management command.
+///
+/// `init`, `path` and `edit` operate on the given or the user configuration file (see
+/// [`ConfigFiles::target`]) while `show` and `validate` take all the files into account.
+pub fn run(command: ConfigCommand, files: &ConfigFiles, args: &Args) -> Result<()> {
+    let target = files.target();
+    match command {
+        ConfigCommand::Init => init(target, args),
+        ConfigCommand::Show => show(files, args),
+        ConfigCommand::Validate => validate(files, args),
+        ConfigCommand::Path => {
+            println!("{}", target.display());
+            if !target.exists() {
+                eprintln!("The file does not exist, run `rpaste config init` to create it.");
+            }
+            // the other files that are merged
+            for path in [&files.system, &files.user, &files.project]
+                .into_iter()
+                .flatten()
+                .filter(|path| path.as_path() != target)
+            {
+                println!("{}", path.display());
+            }
+            Ok(())
+        }
+        ConfigCommand::Edit => edit(target, files, args),
+        ConfigCommand::Trust => {
+            let path = files.project.as_deref().ok_or_else(|| {
+                Error::ConfigError(format!(
+                    "no {PROJECT_CONFIG_FILE} is found in the current directory or its ancestors"
+                ))
+            })?;
+            project::trust(path, &files.trust_store)?;
+            eprintln!("Trusted {}", path.display());
+            Ok(())
+        }
+    }
+}
+
+/// Returns the printable values of the configuration keys.
+fn values(config: &Config) -> Vec<(String, Option<String>)> {
+    fn server_values(prefix: &str, server: &ServerConfig) -> Vec<(String, Option<String>)> {
+        let redact = |token: &Option<_>| token.as_ref().map(|_| String::from("[REDACTED]"));
+        vec![
+            (
+                format!("{prefix}.address"),
+                (!server.address.is_empty()).then(|| format!("{:?}", server.address)),
+            ),
+            (
+                format!("{prefix}.addresses"),
+                (!server.addresses.is_empty()).then(|| format!("{:?}", server.addresses)),
+            ),
+            (
+                format!("{prefix}.public_url"),
+                server.public_url.as_ref().map(|v| format!("{v:?}")),
+            ),
+            (format!("{prefix}.auth_token"), redact(&server.auth_token)),
+            (
+                format!("{prefix}.delete_token"),
+                redact(&server.delete_token),
+            ),
+        ]
+        .into_iter()
+        .chain(
+            [
+                ("auth_token_file", &server.auth_token_file),
+                ("auth_token_command", &server.auth_token_command),
+                ("auth_token_keyring", &server.auth_token_keyring),
+                ("delete_token_file", &server.delete_token_file),
+                ("delete_token_command", &server.delete_token_command),
+                ("delete_token_keyring", &server.delete_token_keyring),
+            ]
+            // the token sources are only shown if they are set
+            .into_iter()
+            .filter_map(|(key, value)| {
+                Some((
+                    format!("{prefix}.{key}"),
+                    Some(format!("{:?}", value.as_ref()?)),
+                ))
+            }),
+        )
+        .collect()
+    }
+    let mut values = server_values("server", &config.server);
+    values.extend([
+        (
+            String::from("paste.oneshot"),
+            config.paste.oneshot.map(|v| v.to_string()),
+        ),
+        (
+            String::from("paste.expire"),
+            config.paste.expire.as_ref().map(|v| format!("{v:?}")),
+        ),
+        (
+            String::from("paste.mirrors"),
+            (!config.paste.mirrors.is_empty()).then(|| format!("{:?}", config.paste.mirrors)),
+        ),
+        (
+            String::from("paste.quorum"),
+            config.paste.quorum.map(|v| v.to_string()),
+        ),
+        (
+            String::from("paste.dedupe"),
+            config.paste.dedupe.map(|v| v.to_string()),
+        ),
+        (
+            String::from("style.prettify"),
+            config.style.as_ref().map(|v| v.prettify.to_string()),
+        ),
+    ]);
+    let mut profiles = config.profiles.iter().collect::<Vec<_>>();
+    profiles.sort_by_key(|(name, _)| name.as_str());
+    for (name, server) in profiles {
+        values.extend(server_values(&format!("profiles.{name}"), server));
+    }
+    values
+}
+
+/// Resolves the settings from the configuration layers, lowest precedence first.
+///
+/// The source of each setting is the last layer that changed its value.
+fn resolve(layers: &[(String, Config)]) -> Vec<Setting> {
+    let mut settings = Vec::<Setting>::new();
+    for (source, config) in layers {
+        for (key, value) in values(config) {
+            match settings.iter_mut().find(|setting| setting.key == key) {
+                Some(setting) if setting.value == value => {}
+                Some(setting) => {
+                    setting.value = value;
+                    setting.source = source.to_string();
+                }
+                None => settings.push(Setting {
+                    key,
+                    value,
+                    source: source.to_string(),
+                }),
+            }
+        }
+    }
+    settings
+}
+
+/// Prints the effective configuration along with the source of each value.
+fn show(files: &ConfigFiles, args: &Args) -> Result<()> {
+    let layers = files.layers()?;
+    let mut configs = vec![(String::from("default"), Config::default())];
+    for (index, layer) in layers.iter().enumerate() {
+        configs.push((
+            layer.path.display().to_string(),
+            Config::from_layers(&layers[..=index])?,
+        ));
+    }
+    let mut config = configs.last().map(|v| v.1.clone()).unwrap_or_default();
+    config.parse_token_files();
+    configs.push((String::from("token source"), config.clone()));
+    config.update_from_env(|name| env::var(name).ok());
+    configs.push((String::from("environment"), config.clone()));
+    config.update_from_args(args);
+    configs.push((String::from("arguments"), config));
+    let settings = resolve(&configs);
+    let width = settings
+        .iter()
+        .map(|setting| setting.key.len())
+        .max()
+        .unwrap_or_default();
+    for setting in settings {
+        match setting.value {
+            Some(value) => println!(
+                "{:width$} = {value}  # {}",
+                setting.key,
+                setting.source.dimmed()
+            ),
+            None => println!("{:width$}   # {}", setting.key, "not set".dimmed()),
+        }
+    }
+    Ok(())
+}
+
+/// Parses the configuration and returns the unknown keys along with it.
+fn parse_strict(value: toml::Value) -> Result<(Config, Vec<String>)> {
+    let mut unknown = Vec::new();
+    let config = serde_ignored::deserialize(value, |path| unknown.push(path.to_string()))?;
+    Ok((config, unknown))
+}
+
+/// Checks the configuration files, the token sources and the reachability of the servers.
+///
+/// Each check is printed and an error is returned if any of them fails.
+fn validate(files: &ConfigFiles, args: &Args) -> Result<()> {
+    if [&files.system, &files.user, &files.project, &files.custom]
+        .iter()
+        .all(|path| path.is_none())
+    {
+        return Err(Error::ConfigError(String::from(
+            "no configuration file is found, run `rpaste config init` to create it",
+        )));
+    }
+    let layers = files.layers()?;
+    let mut problems = 0;
+    let mut check = |ok: bool, message: String| {
+        if ok {
+            println!("{} {message}", "✔".green().bold());
+        } else {
+            println!("{} {message}", "✘".red().bold());
+            problems += 1;
+        }
+    };
+    for layer in &layers {
+        let (_, unknown) = parse_strict(toml::Value::Table(layer.table.clone()))?;
+        check(true, format!("{} is parsed", layer.path.display()));
+        for key in unknown {
+            check(
+                false,
+                format!("unknown key in {}: {key}", layer.path.display()),
+            );
+        }
+    }
+    let mut config = Config::from_layers(&layers)?;
+    config.dry_run = args.dry_run;
+    let mut servers = vec![(String::from("server"), config.clone())];
+    let mut profiles = config.profiles.iter().collect::<Vec<_>>();
+    profiles.sort_by_key(|(name, _)| *name);
+    // the tokens are read below to report the errors
+    for (name, server) in profiles {
+        let config = Config {
+            server: server.clone(),
+            ..config.clone()
+        };
+        servers.push((format!("profiles.{name}"), config));
+    }
+    for (name, mut config) in servers {
+        let candidates = config
+            .server
+            .candidates()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            check(false, format!("{name}.address is not set"));
+            continue;
+        }
+        let errors = config.server.read_tokens();
+        if errors.is_empty() {
+            check(true, format!("{name} tokens are readable"));
+        }
+        for e in errors {
+            check(false, e.to_string());
+        }
+        for address in candidates {
+            if let Err(e) = Url::parse(&address) {
+                check(false, format!("{name} address is invalid: {address}: {e}"));
+                continue;
+            }
+            config.server.address = address;
+            match Uploader::new(&config).retrieve_version() {
+                Ok(version) => check(
+                    true,
+                    format!(
+                        "{} is reachable: rustypaste-server {}",
+                        config.server.address,
+                        version.trim()
+                    ),
+                ),
+                Err(e) => check(
+                    false,
+                    format!("{} is not reachable: {e}", config.server.address),
+                ),
+            }
+        }
+    }
+    if problems != 0 {
+        return Err(Error::ConfigError(format!(
+            "{problems} problems found in the configuration"
+        )));
+    }
+    Ok(())
+}
+
+/// Creates the configuration file at the given path by asking the user for the settings.
+///
+/// The server is tested with the given settings before saving them.
+fn init(path: &Path, args: &Args) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        return Err(Error::ConfigError(String::from(
+            "creating the configuration requires a terminal",
+        )));
+    }
+    if path.exists() && !prompt::confirm(&format!("{} exists, overwrite?", path.display()))? {
+        eprintln!("Aborted.");
+        return Ok(());
+    }
+    let address = loop {
+        let address = prompt::input("Server address (e.g. https://paste.example.com)")?;
+        match Url::parse(&address) {
+            Ok(_) => break address,
+            Err(e) => eprintln!("Invalid address: {e}"),
+        }
+    };
+    let auth_token = prompt::secret("Authentication token (leave empty for none)")?;
+    let delete_token = prompt::secret("Deletion token (leave empty for none)")?;
+    let expire = prompt::input("Expiration time for the links, e.g. 1h (leave empty for none)")?;
+    let prettify = prompt::confirm("Prettify the output?")?;
+
+    let mut config = Config::default();
+    config.server.address = address.clone();
+    config.server.auth_token = (!auth_token.is_empty()).then(|| auth_token.clone().into());
+    config.dry_run = args.dry_run;
+    eprintln!("Testing the server...");
+    match Uploader::new(&config).retrieve_version() {
+        Ok(version) => eprintln!(
+            "{} connected to rustypaste-server {}",
+            "✔".green().bold(),
+            version.trim()
+        ),
+        Err(e) => {
+            eprintln!("{} {e}", "✘".red().bold());
+            if !prompt::confirm("Save the configuration anyway?")? {
+                eprintln!("Aborted.");
+                return Ok(());
+            }
+        }
+    }
+
+    let mut server = toml::Table::new();
+    server.insert(String::from("address"), address.into());
+    if !auth_token.is_empty() {
+        server.insert(String::from("auth_token"), auth_token.into());
+    }
+    if !delete_token.is_empty() {
+        server.insert(String::from("delete_token"), delete_token.into());
+    }
+    let mut paste = toml::Table::new();
+    if !expire.is_empty() {
+        paste.insert(String::from("expire"), expire.into());
+    }
+    let mut style = toml::Table::new();
+    style.insert(String::from("prettify"), prettify.into());
+    let mut table = toml::Table::new();
+    table.insert(String::from("server"), server.into());
+    table.insert(String::from("paste"), paste.into());
+    table.insert(String::from("style"), style.into());
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // the file might contain tokens
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)?
+        .write_all(table.to_string().as_bytes())?;
+    eprintln!("Configuration is saved to {}", path.display());
+    Ok(())
+}
+
+/// Opens the configuration file in the editor (`$VISUAL` or `$EDITOR`) and validates it.
+fn edit(path: &Path, files: &ConfigFiles, args: &Args) -> Result<()> {
+    if !path.exists() {
+        return Err(Error::ConfigError(format!(
+            "{} does not exist, run `rpaste config init` to create it",
+            path.display()
+        )));
+    }
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| String::from(if cfg!(windows) { "notepad" } else { "vi" }));
+    let mut editor = editor.split_whitespace();
+    let program = editor
+        .next()
+        .ok_or_else(|| Error::ConfigError(String::from("the editor is not set")))?;
+    let status = Command::new(program).args(editor).arg(path).status()?;
+    if !status.success() {
+        return Err(Error::ConfigError(format!("editor exited with {status}")));
+    }
+    validate(files, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_unknown_keys() -> Result<()> {
+        let (config, unknown) = parse_strict(toml::from_str(
+            r#"
+            [server]
+            address = "https://paste.example.com"
+            adress = "typo"
+
+            [paste]
+            oneshot = false
+
+            [styles]
+            prettify = true
+            "#,
+        )?)?;
+        assert_eq!("https://paste.example.com", config.server.address);
+        assert_eq!(vec!["server.adress", "styles"], unknown);
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_setting_sources() {
+        let default = Config::default();
+        let mut file = default.clone();
+        file.server.address = String::from("https://file.example.com");
+        file.paste.expire = Some(String::from("1h"));
+        let mut environment = file.clone();
+        environment.update_from_env(|name| match name {
+            "RPASTE_SERVER" => Some(String::from("https://env.example.com")),
+            "RPASTE_AUTH_TOKEN" => Some(String::from("env-secret")),
+            "RPASTE_EXPIRE" => Some(String::from("1h")),
+            _ => None,
+        });
+        let settings = resolve(&[
+            (String::from("default"), default),
+            (String::from("config.toml"), file),
+            (String::from("environment"), environment),
+        ]);
+        let setting = |key: &str| {
+            settings
+                .iter()
+                .find(|setting| setting.key == key)
+                .cloned()
+                .expect("setting should exist")
+        };
+        assert_eq!("environment", setting("server.address").source);
+        assert_eq!(
+            Some(String::from("[REDACTED]")),
+            setting("server.auth_token").value
+        );
+        assert_eq!("config.toml", setting("paste.expire").source);
+        assert_eq!(None, setting("paste.oneshot").value);
+        assert_eq!("default", setting("paste.oneshot").source);
+    }
+}
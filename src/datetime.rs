@@ -0,0 +1,152 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of seconds in a day.
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// Returns the current time as a UNIX timestamp.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Returns the current date in `YYYY-MM-DD` format (UTC).
+pub fn today() -> String {
+    let (year, month, day) = civil_from_days(now().div_euclid(SECONDS_PER_DAY));
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parses an ISO8601 formatted UTC date-time string such as `2024-05-14T12:34:56Z` or
+/// `2024-05-14 12:34:56` into a UNIX timestamp.
+///
+/// Fractional seconds are ignored.
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim().trim_end_matches('Z');
+    let (date, time) = value.split_once(['T', ' '])?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(
+        days_from_civil(year, month as u32, day as u32) * SECONDS_PER_DAY
+            + hour * 3600
+            + minute * 60
+            + second,
+    )
+}
+
+/// Parses a duration such as `30s`, `15min`, `12h`, `7d` or `2w` into seconds.
+pub fn parse_duration(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount = amount.parse::<i64>().ok()?;
+    let unit = match unit.trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => SECONDS_PER_DAY,
+        "w" | "week" | "weeks" => 7 * SECONDS_PER_DAY,
+        _ => return None,
+    };
+    amount.checked_mul(unit)
+}
+
+/// Formats the given number of seconds as a short duration such as `45s`, `12m`, `3h` or `2d`.
+pub fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.abs();
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / SECONDS_PER_DAY),
+    }
+}
+
+/// Converts the number of days since the UNIX epoch to a (year, month, day) date.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a (year, month, day) date to the number of days since the UNIX epoch.
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_days_to_dates() {
+        for (days, date) in [
+            (0, (1970, 1, 1)),
+            (11016, (2000, 2, 29)),
+            (20745, (2026, 10, 19)),
+            (-1, (1969, 12, 31)),
+        ] {
+            assert_eq!(date, civil_from_days(days));
+            assert_eq!(days, days_from_civil(date.0, date.1, date.2));
+        }
+    }
+
+    #[test]
+    fn parse_timestamps() {
+        assert_eq!(Some(0), parse_timestamp("1970-01-01T00:00:00Z"));
+        assert_eq!(
+            Some(1_792_368_000 + 3723),
+            parse_timestamp("2026-10-19 01:02:03")
+        );
+        assert_eq!(
+            Some(1_792_368_000),
+            parse_timestamp("2026-10-19T00:00:00.123456Z")
+        );
+        assert_eq!(None, parse_timestamp("info not available"));
+        assert_eq!(None, parse_timestamp("2026-13-01 00:00:00"));
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(Some(30), parse_duration("30s"));
+        assert_eq!(Some(900), parse_duration("15min"));
+        assert_eq!(Some(7 * 86400), parse_duration("7d"));
+        assert_eq!(Some(2 * 7 * 86400), parse_duration("2 weeks"));
+        assert_eq!(None, parse_duration("7"));
+        assert_eq!(None, parse_duration("d"));
+        assert_eq!(None, parse_duration("7 fortnights"));
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!("45s", format_duration(45));
+        assert_eq!("12m", format_duration(-720));
+        assert_eq!("3h", format_duration(3 * 3600 + 59));
+        assert_eq!("2d", format_duration(2 * 86400 + 1));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::datetime;
+use crate::error::Result;
+use crate::upload::{UploadResult, Uploaded, Uploader};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Entry of an uploaded content in the [`UploadRecord`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// SHA-256 digest of the content.
+    hash: String,
+    /// Name that the content is uploaded as, escaped to fit in a line.
+    name: String,
+    /// Address of the server that the content is uploaded to.
+    server: String,
+    /// Expiration time of the upload (UNIX timestamp), if it expires.
+    expires_at: Option<i64>,
+    /// URL of the upload.
+    url: String,
+}
+
+impl Entry {
+    /// Parses an entry from the tab separated values of the hash, name, server, expiration time
+    /// (`-` if it does not expire) and URL.
+    fn parse(line: &str) -> Option<Self> {
+        let mut values = line.splitn(5, '\t');
+        let (hash, name, server, expires_at, url) = (
+            values.next()?,
+            values.next()?,
+            values.next()?,
+            values.next()?,
+            values.next()?,
+        );
+        Some(Self {
+            hash: hash.to_string(),
+            name: name.to_string(),
+            server: server.to_string(),
+            expires_at: match expires_at {
+                "-" => None,
+                timestamp => Some(timestamp.parse().ok()?),
+            },
+            url: url.to_string(),
+        })
+    }
+
+    /// Returns `true` if the upload is expired at the given time.
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expires_at = match self.expires_at {
+            Some(timestamp) => timestamp.to_string(),
+            None => String::from("-"),
+        };
+        write!(
+            f,
+            "{}\t{}\t{}\t{expires_at}\t{}",
+            self.hash, self.name, self.server, self.url
+        )
+    }
+}
+
+/// Returns the name as it is stored in the record.
+fn escape_name(name: &str) -> String {
+    name.escape_default().to_string()
+}
+
+/// Record of the past uploads, keyed by the SHA-256 digest of the content, the name that it is
+/// uploaded as and the server.
+///
+/// The expired uploads are dropped from the record when it is updated.
+#[derive(Debug)]
+pub struct UploadRecord {
+    /// Path of the record.
+    path: PathBuf,
+}
+
+impl UploadRecord {
+    /// Constructs a new instance.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Returns the entries in the record.
+    fn entries(&self) -> Result<Vec<Entry>> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(contents.lines().filter_map(Entry::parse).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the given entries to the record, dropping the expired ones.
+    fn write(&self, mut entries: Vec<Entry>) -> Result<()> {
+        let now = datetime::now();
+        entries.retain(|entry| !entry.is_expired(now));
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = entries
+            .iter()
+            .map(|entry| format!("{entry}\n"))
+            .collect::<String>();
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+
+    /// Returns the URL of the unexpired upload of the content to the server as the given name,
+    /// if any.
+    pub fn find(&self, hash: &str, name: &str, server: &str) -> Result<Option<String>> {
+        let now = datetime::now();
+        let name = escape_name(name);
+        Ok(self
+            .entries()?
+            .into_iter()
+            .find(|entry| {
+                entry.hash == hash
+                    && entry.name == name
+                    && entry.server == server
+                    && !entry.is_expired(now)
+            })
+            .map(|entry| entry.url))
+    }
+
+    /// Adds the upload of the content to the server as the given name, replacing the previous
+    /// upload of it.
+    pub fn add(
+        &self,
+        hash: &str,
+        name: &str,
+        server: &str,
+        expires_at: Option<i64>,
+        url: &str,
+    ) -> Result<()> {
+        let mut entries = self.entries()?;
+        let name = escape_name(name);
+        entries.retain(|entry| entry.hash != hash || entry.name != name || entry.server != server);
+        entries.push(Entry {
+            hash: hash.to_string(),
+            name,
+            server: server.to_string(),
+            expires_at,
+            url: url.to_string(),
+        });
+        self.write(entries)
+    }
+
+    /// Removes the uploads with the given URL, e.g. after it is deleted from the server.
+    pub fn forget(&self, url: &str) -> Result<()> {
+        let mut entries = self.entries()?;
+        let len = entries.len();
+        entries.retain(|entry| entry.url != url);
+        if entries.len() != len {
+            self.write(entries)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the URL of the content with the given hash if it is already uploaded to the
+    /// server of the uploader as the given name, not expired and still on the server.
+    /// Otherwise, uploads it via the given function and adds it to the record.
+    ///
+    /// The oneshot uploads, the dry runs and the uploads with an expiration time that cannot
+    /// be parsed are not deduplicated.
+    pub fn upload<'a, F>(
+        &self,
+        uploader: &Uploader,
+        label: &'a str,
+        hash: &str,
+        name: &str,
+        upload: F,
+    ) -> UploadResult<'a, Uploaded>
+    where
+        F: FnOnce() -> UploadResult<'a, Uploaded>,
+    {
+        let config = uploader.config();
+        if config.paste.oneshot == Some(true) || config.dry_run {
+            return upload();
+        }
+        let expires_at = match config.paste.expire.as_deref().map(datetime::parse_duration) {
+            Some(Some(duration)) => Some(datetime::now() + duration),
+            Some(None) => return upload(),
+            None => None,
+        };
+        let server = &config.server.address;
+        match self.find(hash, name, server) {
+            // the file might be deleted by the server or by another client
+            Ok(Some(url)) => match Self::check(uploader, &url, hash) {
+                Ok(true) => {
+                    log::info!("{label} is already uploaded to {server}");
+                    let sha256 = config.verify.then(|| hash.to_string());
+                    return UploadResult(label, Ok(Uploaded { url, sha256 }));
+                }
+                Ok(false) => log::info!("{url} is no longer on the server, uploading again"),
+                Err(e) => log::info!("Cannot check {url} ({e}), uploading again"),
+            },
+            Ok(None) => {}
+            Err(e) => eprintln!("Warning: cannot read the upload record: {e}"),
+        }
+        let result = upload();
+        if let Ok(upload) = &result.1 {
+            if let Err(e) = self.add(hash, name, server, expires_at, &upload.url) {
+                eprintln!("Warning: cannot update the upload record: {e}");
+            }
+        }
+        result
+    }
+
+    /// Returns `true` if the uploaded file at the given URL is still on the server.
+    ///
+    /// The content of the file is also compared with the given hash if the uploads are
+    /// verified, see [`Config::verify`](crate::config::Config::verify).
+    fn check(uploader: &Uploader, url: &str, hash: &str) -> Result<bool> {
+        if !uploader.config().verify {
+            return uploader.exists(url);
+        }
+        uploader.verify(url, hash).map(|_| true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum;
+    use std::env;
+
+    #[test]
+    fn parse_entries() {
+        let entry = Entry {
+            hash: String::from("abc"),
+            name: escape_name("a\tb.txt"),
+            server: String::from("https://paste.example.com"),
+            expires_at: None,
+            url: String::from("https://paste.example.com/a.txt"),
+        };
+        assert_eq!(Some(entry.clone()), Entry::parse(&entry.to_string()));
+        assert!(!entry.is_expired(datetime::now()));
+        assert_eq!(
+            None,
+            Entry::parse("abc\ta.txt\thttps://paste.example.com\tnever\turl")
+        );
+        assert!(Entry::parse("abc\ta.txt\t-\t1\turl").is_some_and(|entry| entry.is_expired(1)));
+    }
+
+    #[test]
+    fn find_and_forget_uploads() -> Result<()> {
+        let path = env::temp_dir().join(format!("rpaste-dedupe-test-{}", std::process::id()));
+        let record = UploadRecord::new(path.clone());
+        let hash = checksum::hash("content".as_bytes())?;
+        let server = "https://paste.example.com";
+        let url = "https://paste.example.com/a.txt";
+        let result = record.add(&hash, "a.txt", server, None, url).and_then(|_| {
+            record.add("expired", "a.txt", server, Some(datetime::now() - 1), url)?;
+            let found = (
+                record.find(&hash, "a.txt", server)?,
+                record.find(&hash, "a.txt", "https://other.example.com")?,
+                record.find(&hash, "b.txt", server)?,
+                record.find("expired", "a.txt", server)?,
+            );
+            record.forget(url)?;
+            Ok((found, record.find(&hash, "a.txt", server)?))
+        });
+        fs::remove_file(&path)?;
+        assert_eq!(((Some(url.to_string()), None, None, None), None), result?);
+        Ok(())
+    }
+}
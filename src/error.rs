@@ -15,18 +15,63 @@ pub enum Error {
     /// Error that might occur while uploading files.
     #[error("Upload error: `{0}`")]
     UploadError(String),
+    /// Error that might occur when an uploaded file does not match the content that is sent.
+    #[error("Verification error: `{0}`")]
+    VerifyError(String),
+    /// Error that might occur while splitting or joining files.
+    #[error("Split error: `{0}`")]
+    SplitError(String),
     /// Error that might occur while deleting files from server.
     #[error("Delete error: `{0}`")]
     DeleteError(String),
     /// Error that might occur when no server address is provided.
     #[error("No rustypaste server address is given.")]
     NoServerAddressError,
+    /// Error that might occur when a profile is not found in the configuration.
+    #[error("Unknown profile: `{0}`")]
+    UnknownProfileError(String),
+    /// Error that might occur while parsing JSON.
+    #[error("JSON parsing error: `{0}`")]
+    JsonError(#[from] serde_json::Error),
+    /// Error that might occur while parsing a manifest file.
+    #[error("Manifest error: `{0}`")]
+    ManifestError(String),
     /// Error that might occur during the preparation of the multipart data.
     #[error("Multipart IO error: `{0}`")]
     MultipartIOError(#[from] multipart::client::lazy::LazyError<'static, std::io::Error>),
     /// Error that might occur during parsing URLs.
     #[error("URL parsing error: `{0}`")]
     UrlParseError(#[from] url::ParseError),
+    /// Error that might occur while generating patches with `git`.
+    #[error("Git error: `{0}`")]
+    GitError(String),
+    /// Error that might occur while compiling a regular expression.
+    #[error("Regex error: `{0}`")]
+    RegexError(#[from] regex::Error),
+    /// Error that might occur while parsing a line range.
+    #[error("Line range error: `{0}`")]
+    LineRangeError(String),
+    /// Error that might occur while parsing or rendering a name template.
+    #[error("Name template error: `{0}`")]
+    NameTemplateError(String),
+    /// Error that might occur while listing files.
+    #[error("List error: `{0}`")]
+    ListError(String),
+    /// Error that might occur while parsing a glob pattern.
+    #[error("Glob pattern error: `{0}`")]
+    GlobError(#[from] glob::PatternError),
+    /// Error that might occur while watching a directory.
+    #[error("Watch error: `{0}`")]
+    WatchError(String),
+    /// Error that might occur while picking files.
+    #[error("Pick error: `{0}`")]
+    PickError(String),
+    /// Error that might occur while reading a token from a command or the OS keyring.
+    #[error("Token error: `{0}`")]
+    TokenError(String),
+    /// Error that might occur while managing the configuration file.
+    #[error("Configuration error: `{0}`")]
+    ConfigError(String),
     /// Error that might occur during parsing a progress bar template.
     #[error("Template parsing error: `{0}`")]
     TemplateParseError(#[from] indicatif::style::TemplateError),
@@ -0,0 +1,179 @@
+use crate::config::Config;
+use crate::error::Result;
+use crate::upload::{UploadResult, Uploaded, Uploader};
+use std::cell::Cell;
+use std::time::Duration;
+
+/// Maximum time to wait for a server to respond to the health check.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returns a configuration for each of the
+/// [`ServerConfig::candidates`](crate::config::ServerConfig::candidates), in order.
+///
+/// The configuration is returned as is if there are no server addresses.
+pub fn configs(config: &Config) -> Vec<Config> {
+    let configs = config
+        .server
+        .candidates()
+        .into_iter()
+        .map(|address| {
+            let mut candidate = config.clone();
+            candidate.server.address = address.to_string();
+            candidate
+        })
+        .collect::<Vec<_>>();
+    if configs.is_empty() {
+        return vec![config.clone()];
+    }
+    configs
+}
+
+/// Uploader that fails over to the next server when the server in use is unavailable.
+///
+/// The servers are not checked beforehand: the health of the server in use is only checked
+/// after a request to it fails, and the request is sent again to the first available server
+/// after it. The server is considered to be unavailable if it cannot be connected to, it does
+/// not respond in time or it responds with a server error (5xx).
+#[derive(Debug)]
+pub struct Failover<'a> {
+    /// Uploaders of the candidate servers, in order.
+    uploaders: Vec<Uploader<'a>>,
+    /// Index of the server in use.
+    current: Cell<usize>,
+}
+
+impl<'a> Failover<'a> {
+    /// Constructs a new instance for the servers of the given configurations, see [`configs`].
+    pub fn new(configs: &'a [Config]) -> Self {
+        Self {
+            uploaders: configs.iter().map(Uploader::new).collect(),
+            current: Cell::new(0),
+        }
+    }
+
+    /// Returns the uploader of the server in use.
+    pub fn uploader(&self) -> &Uploader<'a> {
+        &self.uploaders[self.current.get()]
+    }
+
+    /// Returns the address of the server in use.
+    pub fn address(&self) -> &'a str {
+        &self.uploader().config().server.address
+    }
+
+    /// Returns `true` if there are servers to fail over to.
+    fn has_failover(&self) -> bool {
+        self.uploaders.len() > 1 && !self.uploader().config().dry_run
+    }
+
+    /// Returns the uploader of the first available server, starting from the server in use.
+    ///
+    /// This is for the operations that send multiple requests (e.g. watching a directory),
+    /// which should not be switched to another server midway.
+    pub fn select(&self) -> &Uploader<'a> {
+        if self.has_failover() {
+            self.fail_over();
+        }
+        self.uploader()
+    }
+
+    /// Sends the request to the server in use, and to the next available server if the
+    /// server in use turns out to be unavailable after the request fails.
+    pub fn run<T, F>(&self, request: F) -> Result<T>
+    where
+        F: Fn(&Uploader<'a>) -> Result<T>,
+    {
+        loop {
+            match request(self.uploader()) {
+                Err(_) if self.has_failover() && self.fail_over() => {}
+                result => return result,
+            }
+        }
+    }
+
+    /// Runs the upload on the server in use, failing over like [`Failover::run`].
+    ///
+    /// The server that is used is reported if there are servers to fail over to.
+    pub fn upload<F>(&self, upload: F) -> UploadResult<'a, Uploaded>
+    where
+        F: Fn(&Uploader<'a>) -> UploadResult<'a, Uploaded>,
+    {
+        loop {
+            match upload(self.uploader()) {
+                UploadResult(_, Err(_)) if self.has_failover() && self.fail_over() => {}
+                UploadResult(label, Ok(url)) if self.has_failover() => {
+                    eprintln!("{label} is uploaded to {}", self.address());
+                    return UploadResult(label, Ok(url));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Checks the server in use and switches to the first available server after it if it
+    /// is unavailable. The unavailable servers are reported.
+    ///
+    /// Returns `true` if the server is switched.
+    fn fail_over(&self) -> bool {
+        let current = self.current.get();
+        for (index, uploader) in self.uploaders.iter().enumerate().skip(current) {
+            let address = &uploader.config().server.address;
+            log::info!("Checking the health of {address}");
+            match uploader.check_health(HEALTH_CHECK_TIMEOUT) {
+                Ok(()) if index == current => return false,
+                Ok(()) => {
+                    eprintln!("Using the server at {address}");
+                    self.current.set(index);
+                    return true;
+                }
+                Err(e) => eprintln!("Warning: {address} is unavailable: {e}"),
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn list_candidates() {
+        let mut config = Config::default();
+        config.server.addresses = vec![
+            String::from("https://backup.example.com"),
+            String::from("https://paste.example.com"),
+        ];
+        assert_eq!(
+            vec!["https://backup.example.com", "https://paste.example.com"],
+            config.server.candidates()
+        );
+        config.server.address = String::from("https://paste.example.com");
+        assert_eq!(
+            vec!["https://paste.example.com", "https://backup.example.com"],
+            config.server.candidates()
+        );
+        assert_eq!(2, configs(&config).len());
+        assert_eq!(1, configs(&Config::default()).len());
+    }
+
+    #[test]
+    fn stay_on_first_server_if_none_is_available() {
+        let mut config = Config::default();
+        config.server.addresses = vec![
+            String::from("http://127.0.0.1:1"),
+            String::from("http://127.0.0.1:2"),
+        ];
+        let configs = configs(&config);
+        let failover = Failover::new(&configs);
+        let attempts = Cell::new(0);
+        let result = failover.run(|_| -> Result<()> {
+            attempts.set(attempts.get() + 1);
+            Err(Error::UploadError(String::from("unavailable")))
+        });
+        assert!(result.is_err());
+        assert_eq!(1, attempts.get());
+        assert_eq!("http://127.0.0.1:1", failover.address());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::error::{Error, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Extension of the uploaded patch files.
+const PATCH_EXTENSION: &str = "patch";
+
+/// Source of a patch generated by `git`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitSource {
+    /// Changes in the working tree, optionally compared against a revision or range.
+    Diff(Option<String>),
+    /// A commit or a range of commits.
+    Show(String),
+    /// Changes staged for the next commit.
+    Staged,
+}
+
+/// Patch generated from a git repository.
+#[derive(Debug)]
+pub struct Patch {
+    /// File name to use for the upload (`<repo>-<shortsha>.patch`).
+    pub name: String,
+    /// Contents of the patch.
+    pub data: Vec<u8>,
+}
+
+impl GitSource {
+    /// Returns the arguments to pass to `git` for generating the patch.
+    fn command_args(&self) -> Vec<&str> {
+        match self {
+            Self::Diff(None) => vec!["diff"],
+            Self::Diff(Some(rev)) => vec!["diff", "--end-of-options", rev],
+            Self::Show(rev) => vec!["show", "--end-of-options", rev],
+            Self::Staged => vec!["diff", "--staged"],
+        }
+    }
+
+    /// Returns the revision that is used for naming the patch.
+    ///
+    /// For ranges such as `main..feature`, the end of the range is used.
+    fn naming_revision(&self) -> &str {
+        let rev = match self {
+            Self::Diff(Some(rev)) | Self::Show(rev) => rev.as_str(),
+            Self::Diff(None) | Self::Staged => "HEAD",
+        };
+        match rev.rsplit_once("..") {
+            Some((_, end)) => end.trim_start_matches('.'),
+            None => rev,
+        }
+    }
+
+    /// Generates the patch by invoking `git` in the given directory.
+    pub fn patch(&self, dir: &Path) -> Result<Patch> {
+        let toplevel = git(dir, &["rev-parse", "--show-toplevel"])
+            .map_err(|_| Error::GitError(format!("{} is not a git repository", dir.display())))?;
+        let toplevel = String::from_utf8_lossy(&toplevel);
+        let repo = Path::new(toplevel.trim())
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("repo"));
+        let revision = match self.naming_revision() {
+            "" => "HEAD",
+            rev => rev,
+        };
+        // a repository without any commits does not have a HEAD to name the patch after
+        let short_sha = git(dir, &["rev-parse", "--short", "--end-of-options", revision])
+            .map(|sha| String::from_utf8_lossy(&sha).trim().to_string())
+            .unwrap_or_else(|_| String::from("initial"));
+        let data = git(dir, &self.command_args())?;
+        if data.is_empty() {
+            return Err(Error::GitError(String::from("no changes to upload")));
+        }
+        Ok(Patch {
+            name: format!("{repo}-{short_sha}.{PATCH_EXTENSION}"),
+            data,
+        })
+    }
+}
+
+/// Runs `git` with the given arguments and returns the standard output.
+fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| Error::GitError(format!("failed to run git: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::GitError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn naming_revision_uses_end_of_range() {
+        assert_eq!(GitSource::Diff(None).naming_revision(), "HEAD");
+        assert_eq!(GitSource::Staged.naming_revision(), "HEAD");
+        assert_eq!(
+            GitSource::Show(String::from("v1.0")).naming_revision(),
+            "v1.0"
+        );
+        assert_eq!(
+            GitSource::Diff(Some(String::from("main..feature"))).naming_revision(),
+            "feature"
+        );
+        assert_eq!(
+            GitSource::Diff(Some(String::from("main...feature"))).naming_revision(),
+            "feature"
+        );
+    }
+
+    #[test]
+    fn patch_outside_repository_fails() {
+        let dir = env::temp_dir().join(format!("rpaste-git-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temporary directory should be created");
+        let result = GitSource::Staged.patch(&dir);
+        std::fs::remove_dir_all(&dir).expect("temporary directory should be removed");
+
+        assert!(matches!(
+            result,
+            Err(Error::GitError(message)) if message.ends_with("is not a git repository")
+        ));
+    }
+
+    #[test]
+    fn revision_is_not_an_option() {
+        let output = env::temp_dir().join(format!("rpaste-git-output-{}", std::process::id()));
+        let revision = format!("--output={}", output.display());
+        let result = GitSource::Show(revision).patch(Path::new(env!("CARGO_MANIFEST_DIR")));
+
+        assert!(result.is_err());
+        assert!(!output.exists());
+    }
+}
@@ -5,26 +5,88 @@
 
 /// Command-line argument parser.
 pub mod args;
+/// SHA-256 checksums.
+pub mod checksum;
 /// Configuration file parser.
 pub mod config;
+/// Configuration management commands.
+pub mod configure;
+/// Date and time utilities.
+pub mod datetime;
+/// Upload deduplication.
+pub mod dedupe;
 /// Custom error implementation.
 pub mod error;
+/// Server failover.
+pub mod failover;
+/// Git integration.
+pub mod git;
+/// File list formatting.
+pub mod list;
+/// Debug logging.
+pub mod logger;
+/// Batch manifest parser.
+pub mod manifest;
+/// Mirrored uploads.
+pub mod mirror;
+/// File naming.
+pub mod naming;
+/// Fuzzy finder.
+pub mod pick;
+/// Project-local configuration.
+pub mod project;
+/// Interactive prompts.
+pub mod prompt;
+/// Line selection.
+pub mod select;
+/// Split uploads.
+pub mod split;
+/// Interactive terminal UI.
+pub mod tui;
 /// Upload handler.
 pub mod upload;
+/// Directory watcher.
+#[cfg(target_os = "linux")]
+pub mod watch;
 
-use crate::args::Args;
-use crate::config::Config;
+use crate::args::{Args, Command};
+use crate::config::{Config, ConfigFiles};
+use crate::dedupe::UploadRecord;
 use crate::error::{Error, Result};
-use crate::upload::Uploader;
+use crate::failover::Failover;
+use crate::list::{ItemFilter, ListOptions};
+use crate::manifest::Manifest;
+use crate::mirror::Mirror;
+use crate::naming::NameTemplate;
+use crate::project::TrustStore;
+use crate::select::{Pattern, Selection};
+use crate::split::Splitter;
+use crate::upload::{ListItem, UploadResult, Uploaded, Uploader};
 use colored::Colorize;
 use etcetera::BaseStrategy;
-use std::fs;
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::bytes::Regex;
+use std::env;
+use std::fs::File;
 use std::io::IsTerminal;
-use std::io::{self, Read};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
 
 /// Default name of the configuration file.
 const CONFIG_FILE: &str = "config.toml";
 
+/// Path of the system-wide configuration file.
+#[cfg(target_family = "unix")]
+const SYSTEM_CONFIG_FILE: Option<&str> = Some("/etc/rustypaste/config.toml");
+#[cfg(not(target_family = "unix"))]
+const SYSTEM_CONFIG_FILE: Option<&str> = None;
+
+/// Name of the file that lists the trusted project configuration files, in the state directory.
+const TRUST_STORE_FILE: &str = "trusted_projects";
+
+/// Name of the record of the past uploads, in the state directory.
+const UPLOAD_RECORD_FILE: &str = "uploads";
+
 /// Returns `true` when input should be read from stdin.
 ///
 /// An explicit `-` argument always selects stdin. Otherwise, stdin is used only
@@ -36,87 +98,554 @@ fn should_read_stdin(files: &[String], stdin_is_tty: bool) -> bool {
     files.iter().any(|f| f == "-") || (files.is_empty() && !stdin_is_tty)
 }
 
-/// Runs `rpaste`.
-pub fn run(args: Args) -> Result<()> {
-    let mut config = Config::default();
-    if let Some(ref config_path) = args.config {
-        config = toml::from_str(&fs::read_to_string(config_path)?)?
-    } else {
-        // cannot panic - see https://github.com/lunacookies/etcetera/issues/42
-        let strategy = etcetera::choose_base_strategy()
-            .expect("cannot determine current OS's default strategy (layout)");
-        for path in [
-            strategy.config_dir().join("rustypaste").join(CONFIG_FILE),
-            // paths for backwards compatibility
-            #[cfg(target_family = "unix")]
-            strategy
-                .home_dir()
-                .to_path_buf()
-                .join(".rustypaste")
-                .join(CONFIG_FILE),
-            #[cfg(target_os = "macos")]
-            strategy
-                .home_dir()
-                .to_path_buf()
-                .join("Library/Application Support/rustypaste")
-                .join(CONFIG_FILE),
-        ]
-        .iter()
-        {
-            if path.exists() {
-                config = toml::from_str(&fs::read_to_string(path)?)?;
-                break;
-            }
+/// Returns the paths to look for the configuration file at, in the order of precedence.
+fn global_config_paths() -> Vec<PathBuf> {
+    // cannot panic - see https://github.com/lunacookies/etcetera/issues/42
+    let strategy = etcetera::choose_base_strategy()
+        .expect("cannot determine current OS's default strategy (layout)");
+    vec![
+        strategy.config_dir().join("rustypaste").join(CONFIG_FILE),
+        // paths for backwards compatibility
+        #[cfg(target_family = "unix")]
+        strategy
+            .home_dir()
+            .to_path_buf()
+            .join(".rustypaste")
+            .join(CONFIG_FILE),
+        #[cfg(target_os = "macos")]
+        strategy
+            .home_dir()
+            .to_path_buf()
+            .join("Library/Application Support/rustypaste")
+            .join(CONFIG_FILE),
+    ]
+}
+
+/// Returns the directory of the files that are written by `rpaste` itself (e.g. the record of
+/// the past uploads), so that they are kept out of the configuration directory.
+///
+/// The data directory is used on the platforms without a state directory.
+fn state_dir() -> PathBuf {
+    let strategy = etcetera::choose_base_strategy()
+        .expect("cannot determine current OS's default strategy (layout)");
+    strategy
+        .state_dir()
+        .unwrap_or_else(|| strategy.data_dir())
+        .join("rustypaste")
+}
+
+/// Returns the first user configuration file that exists in the global paths, if any.
+fn find_user_config() -> Option<PathBuf> {
+    for path in global_config_paths() {
+        if path.exists() {
+            return Some(path);
         }
+        log::debug!("No configuration file at {path:?}");
+    }
+    None
+}
+
+/// Runs `rpaste`.
+pub fn run(mut args: Args) -> Result<()> {
+    logger::init(logger::level(args.verbose));
+    let default = global_config_paths().remove(0);
+    let files = ConfigFiles {
+        system: SYSTEM_CONFIG_FILE
+            .map(PathBuf::from)
+            .filter(|path| path.exists()),
+        user: find_user_config(),
+        project: project::find(&env::current_dir()?),
+        custom: args.config.clone(),
+        trust_store: TrustStore::new(state_dir().join(TRUST_STORE_FILE)),
+        default,
+    };
+    if let Some(Command::Config(command)) = args.command {
+        return configure::run(command, &files, &args);
     }
-    config.parse_token_files();
+    let mut config = Config::from_layers(&files.layers()?)?;
+    config.update_from_env(|name| env::var(name).ok());
     config.update_from_args(&args);
-    if config.server.address.is_empty() {
+    // the tokens of the profiles are read when they are used
+    config.server.parse_token_files();
+    let servers = failover::configs(&config);
+    if servers[0].server.address.is_empty() {
         return Err(Error::NoServerAddressError);
     }
+    let failover = Failover::new(&servers);
+    if config.verify && config.paste.oneshot == Some(true) {
+        eprintln!("Warning: the oneshot files are not verified since fetching them deletes them");
+    }
 
-    let uploader = Uploader::new(&config);
+    let prettify = args.prettify
+        || config
+            .style
+            .as_ref()
+            .map(|style| style.prettify)
+            .unwrap_or(false);
+    let record = UploadRecord::new(state_dir().join(UPLOAD_RECORD_FILE));
     if args.print_server_version {
-        println!("rustypaste-server {}", uploader.retrieve_version()?.trim());
+        let version = failover.run(|uploader| uploader.retrieve_version())?;
+        if config.dry_run {
+            println!("{version}");
+        } else {
+            println!("rustypaste-server {}", version.trim());
+        }
         return Ok(());
     }
 
+    if args.pick {
+        let (candidates, prompt) = if args.delete {
+            let items = failover.run(|uploader| uploader.retrieve_items())?;
+            (
+                items.into_iter().map(|item| item.file_name).collect(),
+                "delete",
+            )
+        } else {
+            (pick::local_files(&args.files)?, "upload")
+        };
+        // the picked files are only described in dry runs
+        let prompt = if config.dry_run {
+            format!("{prompt} (dry run)")
+        } else {
+            prompt.to_string()
+        };
+        args.files = pick::pick(candidates, &prompt)?;
+        if args.files.is_empty() {
+            eprintln!("No files are picked.");
+            return Ok(());
+        }
+    }
+
+    if args.command == Some(Command::Tui) {
+        let items = failover.run(|uploader| uploader.retrieve_items())?;
+        return tui::run(failover.uploader(), items, failover.address());
+    }
+
     if args.list_files {
-        let prettify = args.prettify
-            || config
-                .style
-                .as_ref()
-                .map(|style| style.prettify)
-                .unwrap_or(false);
-        uploader.retrieve_list(&mut io::stdout(), prettify)?;
+        let options = ListOptions {
+            sort: args.sort,
+            reverse: args.reverse,
+            filters: ItemFilter::parse_all(&args.filters)?,
+            prettify,
+            format: args.list_format,
+            width: console::Term::stdout()
+                .size_checked()
+                .map(|(_, width)| usize::from(width)),
+        };
+        return failover.run(|uploader| uploader.retrieve_list(&mut io::stdout(), &options));
+    }
+
+    if args.delete
+        && (args.delete_all
+            || !args.delete_match.is_empty()
+            || args.delete_type.is_some()
+            || args.older_than.is_some())
+    {
+        if !args.files.is_empty() {
+            return Err(Error::DeleteError(String::from(
+                "file names cannot be combined with --match, --type, --older-than or --all",
+            )));
+        }
+        let mut filters = Vec::new();
+        if !args.delete_match.is_empty() {
+            filters.push(ItemFilter::names(&args.delete_match)?);
+        }
+        if let Some(ref item_type) = args.delete_type {
+            filters.push(ItemFilter::item_type(item_type)?);
+        }
+        if let Some(age) = args.older_than {
+            filters.push(ItemFilter::CreatedBefore(datetime::now() - age));
+        }
+        let options = ListOptions {
+            filters,
+            ..ListOptions::default()
+        };
+        // the list is retrieved in dry runs as well since the files to delete are only known
+        // after retrieving it
+        let items = options.apply(failover.run(|uploader| uploader.retrieve_items())?);
+        // the files are deleted from the server that they are listed from
+        let uploader = failover.uploader();
+        if config.dry_run {
+            let results = items
+                .iter()
+                .map(|item| {
+                    let UploadResult(file, result) = uploader.delete_file(&item.file_name);
+                    UploadResult(file, result.map(Uploaded::from))
+                })
+                .collect::<Vec<_>>();
+            print_results(&results, prettify);
+            return Ok(());
+        }
+        return delete_items(uploader, &record, &items, args.yes, prettify);
+    }
+
+    if let Some(ref dir) = args.watch {
+        #[cfg(target_os = "linux")]
+        {
+            let watcher = watch::Watcher::new(
+                failover.select(),
+                dir.to_path_buf(),
+                &args.include,
+                args.watch_manifest.clone(),
+            )?;
+            watcher.run(|result| print_results(&[result], prettify))?;
+            return Ok(());
+        }
+        #[cfg(not(target_os = "linux"))]
+        return Err(Error::WatchError(format!(
+            "cannot watch {}: only supported on Linux",
+            dir.display()
+        )));
+    }
+
+    if let Some(ref path) = args.manifest {
+        let manifest = Manifest::parse(path)?;
+        let mut base = failover.select().config().clone();
+        let configs = manifest
+            .entry
+            .iter()
+            .map(|entry| entry.config(&mut base))
+            .collect::<Result<Vec<_>>>()?;
+        let results = manifest.upload(&configs);
+        print_results(&results, prettify);
+        let failed = results.iter().filter(|result| result.1.is_err()).count();
+        eprintln!(
+            "{} entries: {} uploaded, {} failed",
+            results.len(),
+            results.len() - failed,
+            failed
+        );
+        if failed != 0 {
+            return Err(Error::ManifestError(format!(
+                "{failed} of {} entries failed",
+                results.len()
+            )));
+        }
+        return Ok(());
+    }
+
+    if let Some(ref url) = args.join {
+        println!("{}", split::join(&config, url, &env::current_dir()?)?);
         return Ok(());
     }
 
+    if let Some(part_size) = args.split {
+        // fetching the oneshot parts while joining would delete them
+        if config.paste.oneshot == Some(true) {
+            return Err(Error::SplitError(String::from(
+                "the oneshot files cannot be split",
+            )));
+        }
+        let splitter = Splitter::new(failover.select(), part_size, args.jobs);
+        let results = args
+            .files
+            .iter()
+            .map(|file| splitter.upload_file(file))
+            .collect::<Vec<_>>();
+        print_results(&results, prettify);
+        return Ok(());
+    }
+
+    let patch = match args.git {
+        Some(ref source) => Some(source.patch(&env::current_dir()?)?),
+        None => None,
+    };
+    let pattern = match args.grep {
+        Some(ref regex) => Some(Pattern {
+            regex: Regex::new(regex)?,
+            context: args.context,
+        }),
+        None => None,
+    };
+    let mirror_servers = config
+        .paste
+        .mirrors
+        .clone()
+        .iter()
+        .map(|profile| Ok(failover::configs(&config.with_profile(profile)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let mirror = Mirror::new(
+        [Failover::new(&servers)]
+            .into_iter()
+            .chain(mirror_servers.iter().map(|servers| Failover::new(servers)))
+            .collect(),
+        config.paste.quorum,
+    )?;
+    // content hashes are only computed when deduplicating
+    let content_hash = |content: Result<String>| {
+        if config.paste.dedupe == Some(true) {
+            content.ok()
+        } else {
+            None
+        }
+    };
     let mut results = Vec::new();
-    if let Some(ref url) = args.url {
-        results.push(uploader.upload_url(url));
-    } else if let Some(ref remote_url) = args.remote {
-        results.push(uploader.upload_remote_url(remote_url));
-    } else if should_read_stdin(&args.files, std::io::stdin().is_terminal()) {
+    // number of the mirrored uploads that did not reach the quorum
+    let mut failed = 0;
+    for url in &args.urls {
+        if !mirror.upload(&mut results, |uploader| uploader.upload_url(url)) {
+            failed += 1;
+        }
+    }
+    for remote_url in &args.remotes {
+        if !mirror.upload(&mut results, |uploader| {
+            uploader.upload_remote_url(remote_url)
+        }) {
+            failed += 1;
+        }
+    }
+    // stdin is not read implicitly when URLs are given, only via `-`
+    let stdin_is_tty = std::io::stdin().is_terminal() || !results.is_empty();
+    if let Some(ref patch) = patch {
+        let name = args.filename.as_deref().unwrap_or(&patch.name);
+        let hash = content_hash(checksum::hash(&*patch.data));
+        if !mirror.upload(&mut results, |uploader| {
+            upload_once(&record, hash.as_deref(), uploader, name, name, || {
+                uploader.upload_stream_as(name, name, &*patch.data)
+            })
+        }) {
+            failed += 1;
+        }
+    } else if should_read_stdin(&args.files, stdin_is_tty) {
         let mut buffer = Vec::new();
         let mut stdin = io::stdin();
         stdin.read_to_end(&mut buffer)?;
-        results.push(uploader.upload_stream(&*buffer));
-    } else {
+        let hash = content_hash(checksum::hash(&*buffer));
+        if !mirror.upload(&mut results, |uploader| {
+            let name = uploader.config().paste.filename.as_deref();
+            upload_once(
+                &record,
+                hash.as_deref(),
+                uploader,
+                "stream",
+                name.unwrap_or("file"),
+                || uploader.upload_stream(&*buffer),
+            )
+        }) {
+            failed += 1;
+        }
+    } else if args.delete {
         for file in args.files.iter() {
-            if !args.delete {
-                results.push(uploader.upload_file(file))
-            } else {
-                results.push(uploader.delete_file(file))
+            // paste URLs are deleted from the server that they belong to
+            let result = match config.locate_paste(file) {
+                Ok(Some((config, file_name))) => {
+                    let uploader = Uploader::new(&config);
+                    let result = uploader.delete(&file_name);
+                    if result.is_ok() {
+                        forget_upload(&record, &uploader, &file_name);
+                    }
+                    result
+                }
+                Ok(None) => failover.run(|uploader| {
+                    let result = uploader.delete(file);
+                    if result.is_ok() {
+                        forget_upload(&record, uploader, file);
+                    }
+                    result
+                }),
+                Err(e) => Err(e),
+            };
+            results.push(UploadResult(file, result.map(Uploaded::from)));
+        }
+    } else {
+        let template = match args.name_template {
+            Some(ref template) => Some(NameTemplate::parse(template)?),
+            None => None,
+        };
+        let mut uploads = Vec::new();
+        for (index, file) in args.files.iter().enumerate() {
+            let (local, rename) = naming::split_rename(file);
+            let (path, range) = select::split_line_range(local)?;
+            let selection = Selection {
+                range,
+                pattern: pattern.as_ref(),
+            };
+            let filename = match (rename, &template) {
+                (Some(name), _) => Ok(Some(name.to_string())),
+                (None, Some(template)) => template.render(path, index + 1).map(Some),
+                // the configured file name (e.g. via `-n`) takes precedence over the range
+                (None, None) if !selection.is_empty() => Ok(Some(
+                    config
+                        .paste
+                        .filename
+                        .clone()
+                        .unwrap_or_else(|| selection.file_name(path)),
+                )),
+                (None, None) => Ok(None),
+            };
+            uploads.push((file, path, selection, filename));
+        }
+        let names = uploads
+            .iter()
+            .filter_map(|(_, path, _, filename)| match filename {
+                Ok(Some(filename)) => Some(filename.as_str()),
+                Ok(None) => config
+                    .paste
+                    .filename
+                    .as_deref()
+                    .or_else(|| Path::new(path).file_name()?.to_str()),
+                Err(_) => None,
+            });
+        for name in naming::duplicate_names(names) {
+            eprintln!("Warning: multiple files are uploaded as `{name}`");
+        }
+        for (file, path, selection, filename) in uploads {
+            let reached = match filename {
+                Ok(filename) if selection.is_empty() => {
+                    let hash = content_hash(
+                        File::open(path)
+                            .map_err(Error::from)
+                            .and_then(checksum::hash),
+                    );
+                    mirror.upload(&mut results, |uploader| {
+                        let name = filename
+                            .as_deref()
+                            .or(uploader.config().paste.filename.as_deref())
+                            .or_else(|| Path::new(path).file_name()?.to_str())
+                            .unwrap_or(path);
+                        upload_once(&record, hash.as_deref(), uploader, file, name, || {
+                            uploader.upload_file_as(file, path, filename.as_deref())
+                        })
+                    })
+                }
+                Ok(filename) => {
+                    let filename = filename.unwrap_or_else(|| selection.file_name(path));
+                    let hash = content_hash(
+                        File::open(path)
+                            .map_err(Error::from)
+                            .and_then(|f| checksum::hash(selection.apply(BufReader::new(f)))),
+                    );
+                    mirror.upload(&mut results, |uploader| {
+                        upload_once(&record, hash.as_deref(), uploader, file, &filename, || {
+                            uploader.upload_file_lines(file, path, &filename, selection)
+                        })
+                    })
+                }
+                Err(e) => {
+                    results.push(UploadResult(file, Err(e)));
+                    true
+                }
+            };
+            if !reached {
+                failed += 1;
             }
         }
     }
-    let prettify = args.prettify
-        || config
-            .style
-            .as_ref()
-            .map(|style| style.prettify)
-            .unwrap_or(false);
+    print_results(&results, prettify);
+    if failed != 0 {
+        return Err(Error::UploadError(format!(
+            "{failed} uploads did not reach the quorum of {} out of {} servers",
+            mirror.quorum(),
+            mirror_servers.len() + 1
+        )));
+    }
+
+    Ok(())
+}
+
+/// Runs the upload unless the content with the given hash is already uploaded to the server of
+/// the uploader as the given name, see [`UploadRecord::upload`].
+///
+/// The hash is only given when deduplicating the uploads.
+fn upload_once<'a, F>(
+    record: &UploadRecord,
+    hash: Option<&str>,
+    uploader: &Uploader<'a>,
+    label: &'a str,
+    name: &str,
+    upload: F,
+) -> UploadResult<'a, Uploaded>
+where
+    F: FnOnce() -> UploadResult<'a, Uploaded>,
+{
+    match hash {
+        Some(hash) => record.upload(uploader, label, hash, name, upload),
+        None => upload(),
+    }
+}
+
+/// Removes the deleted file from the record of the past uploads.
+fn forget_upload(record: &UploadRecord, uploader: &Uploader, file_name: &str) {
+    if uploader.config().dry_run {
+        return;
+    }
+    if let Err(e) = uploader
+        .retrieve_url(file_name)
+        .and_then(|url| record.forget(url.as_str()))
+    {
+        eprintln!("Warning: cannot update the upload record: {e}");
+    }
+}
+
+/// Deletes the given items from the server after showing them and asking for confirmation.
+///
+/// The confirmation is skipped if `yes` is set, it is required when stdin is not a terminal.
+fn delete_items(
+    uploader: &Uploader,
+    record: &UploadRecord,
+    items: &[ListItem],
+    yes: bool,
+    prettify: bool,
+) -> Result<()> {
+    if items.is_empty() {
+        eprintln!("No matching files on server.");
+        return Ok(());
+    }
+    eprintln!("The following files will be deleted:");
+    for item in items {
+        eprintln!("  {}", item.file_name);
+    }
+    if !yes {
+        if !io::stdin().is_terminal() {
+            return Err(Error::DeleteError(String::from(
+                "confirmation is required, use --yes to delete without asking",
+            )));
+        }
+        if !prompt::confirm(&format!("Delete {} files?", items.len()))? {
+            eprintln!("Aborted.");
+            return Ok(());
+        }
+    }
+    let progress_bar = ProgressBar::new(items.len() as u64);
+    progress_bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{msg:.green.bold} [{wide_bar:.cyan/blue}] {pos}/{len}")?
+            .progress_chars("#>-"),
+    );
+    progress_bar.set_message("Deleting");
+    let results = items
+        .iter()
+        .map(|item| {
+            let UploadResult(file, result) = uploader.delete_file(&item.file_name);
+            if result.is_ok() {
+                forget_upload(record, uploader, &item.file_name);
+            }
+            progress_bar.inc(1);
+            UploadResult(file, result.map(Uploaded::from))
+        })
+        .collect::<Vec<_>>();
+    progress_bar.finish_and_clear();
+    print_results(&results, prettify);
+    let failed = results.iter().filter(|result| result.1.is_err()).count();
+    eprintln!(
+        "{} of {} files deleted",
+        results.len() - failed,
+        results.len()
+    );
+    if failed != 0 {
+        return Err(Error::DeleteError(format!(
+            "{failed} of {} files could not be deleted",
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Prints the results of the uploads (or deletions).
+///
+/// Prettified output aligns the results by the length of the longest file name. The verified
+/// uploads are followed by their SHA-256 digests.
+fn print_results(results: &[UploadResult<'_, Uploaded>], prettify: bool) {
     let format_padding = prettify
         .then(|| results.iter().map(|v| v.0.len()).max())
         .flatten()
@@ -137,12 +666,14 @@ pub fn run(args: Args) -> Result<()> {
             String::new()
         };
         match result {
-            Ok(url) => println!("{}{}", data, url.trim()),
+            Ok(Uploaded {
+                url,
+                sha256: Some(sha256),
+            }) => println!("{}{} (sha256: {sha256})", data, url.trim()),
+            Ok(Uploaded { url, sha256: None }) => println!("{}{}", data, url.trim()),
             Err(e) => eprintln!("{data}{e}"),
         }
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
@@ -0,0 +1,626 @@
+use crate::datetime;
+use crate::error::{Error, Result};
+use crate::upload::ListItem;
+use glob::Pattern;
+use std::cmp::Ordering;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Minimum width of the file name column when the table is truncated.
+const MIN_NAME_WIDTH: usize = 8;
+
+/// Headers of the table columns.
+pub const HEADERS: [&str; 5] = ["Name", "Size", "Type", "Created", "Expires"];
+
+/// Columns of the exported rows, in order.
+const COLUMNS: [&str; 5] = [
+    "file_name",
+    "file_size",
+    "item_type",
+    "creation_date_utc",
+    "expires_at_utc",
+];
+
+/// Units for human-readable file sizes.
+const SIZE_UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+/// Key for sorting the file list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// File name.
+    Name,
+    /// File size.
+    Size,
+    /// Creation timestamp.
+    Created,
+    /// Expiration timestamp.
+    Expiry,
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "name" => Ok(Self::Name),
+            "size" => Ok(Self::Size),
+            "created" => Ok(Self::Created),
+            "expiry" => Ok(Self::Expiry),
+            _ => Err(Error::ListError(format!(
+                "invalid sort key: {s} (expected name, size, created or expiry)"
+            ))),
+        }
+    }
+}
+
+/// Export format of the file list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// JSON array.
+    Json,
+    /// Newline delimited JSON, one item per line.
+    Ndjson,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// Tab-separated values with a header row.
+    Tsv,
+}
+
+impl FromStr for ListFormat {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            _ => Err(Error::ListError(format!(
+                "invalid format: {s} (expected csv, tsv, ndjson or json)"
+            ))),
+        }
+    }
+}
+
+/// Filter for the file list.
+#[derive(Debug, Clone)]
+pub enum ItemFilter {
+    /// Types of the item (`file`, `url` or `oneshot`), any of which should match.
+    Types(Vec<String>),
+    /// Glob patterns for the file name, any of which should match.
+    Names(Vec<Pattern>),
+    /// Items created before the given UNIX timestamp.
+    CreatedBefore(i64),
+}
+
+impl ItemFilter {
+    /// Parses a filter, which is either an item type or a glob pattern for the file name.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "file" | "url" | "oneshot" => Ok(Self::Types(vec![value.to_string()])),
+            _ => Ok(Self::Names(vec![Pattern::new(value)?])),
+        }
+    }
+
+    /// Parses the given filters, combining the item types and the glob patterns into a single
+    /// filter each so that the item should match any of the types and any of the patterns.
+    pub fn parse_all<S: AsRef<str>>(values: &[S]) -> Result<Vec<Self>> {
+        let mut types = Vec::new();
+        let mut patterns = Vec::new();
+        for value in values {
+            match Self::parse(value.as_ref())? {
+                Self::Types(item_types) => types.extend(item_types),
+                Self::Names(names) => patterns.extend(names),
+                Self::CreatedBefore(_) => {}
+            }
+        }
+        let mut filters = Vec::new();
+        if !types.is_empty() {
+            filters.push(Self::Types(types));
+        }
+        if !patterns.is_empty() {
+            filters.push(Self::Names(patterns));
+        }
+        Ok(filters)
+    }
+
+    /// Parses the glob patterns for the file name, any of which should match.
+    pub fn names<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        Ok(Self::Names(
+            patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern.as_ref()))
+                .collect::<std::result::Result<_, _>>()?,
+        ))
+    }
+
+    /// Parses an item type filter (`file`, `url` or `oneshot`).
+    pub fn item_type(value: &str) -> Result<Self> {
+        match value {
+            "file" | "url" | "oneshot" => Ok(Self::Types(vec![value.to_string()])),
+            _ => Err(Error::ListError(format!(
+                "invalid type: {value} (expected file, url or oneshot)"
+            ))),
+        }
+    }
+
+    /// Returns `true` if the item matches the filter.
+    ///
+    /// `url` also matches one shot URLs and `oneshot` matches both one shot files and URLs.
+    pub fn matches(&self, item: &ListItem) -> bool {
+        match self {
+            Self::Types(item_types) => {
+                item_types.iter().any(|item_type| match item_type.as_str() {
+                    "url" => item.item_type.ends_with("url"),
+                    "oneshot" => item.item_type.starts_with("oneshot"),
+                    item_type => item.item_type == item_type,
+                })
+            }
+            Self::Names(patterns) => patterns
+                .iter()
+                .any(|pattern| pattern.matches(&item.file_name)),
+            Self::CreatedBefore(timestamp) => item
+                .creation_date_utc
+                .as_deref()
+                .and_then(datetime::parse_timestamp)
+                .is_some_and(|created| created < *timestamp),
+        }
+    }
+}
+
+/// Options for listing the files on the server.
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    /// Key to sort the files by.
+    pub sort: Option<SortKey>,
+    /// Whether if the order will be reversed.
+    pub reverse: bool,
+    /// Filters that the files should match.
+    pub filters: Vec<ItemFilter>,
+    /// Whether if the output will be prettified.
+    pub prettify: bool,
+    /// Export format, which takes precedence over prettifying.
+    pub format: Option<ListFormat>,
+    /// Maximum width of the prettified output.
+    pub width: Option<usize>,
+}
+
+impl ListOptions {
+    /// Returns `true` if the list should be printed as returned by the server.
+    pub fn is_raw(&self) -> bool {
+        !self.prettify
+            && self.format.is_none()
+            && self.sort.is_none()
+            && !self.reverse
+            && self.filters.is_empty()
+    }
+
+    /// Filters and sorts the given items.
+    pub fn apply(&self, mut items: Vec<ListItem>) -> Vec<ListItem> {
+        items.retain(|item| self.filters.iter().all(|filter| filter.matches(item)));
+        if let Some(sort) = self.sort {
+            items.sort_by(|a, b| compare(a, b, sort));
+        }
+        if self.reverse {
+            items.reverse();
+        }
+        items
+    }
+}
+
+/// Compares the items by the given key.
+///
+/// Items without a value for the key are placed last.
+fn compare(a: &ListItem, b: &ListItem, key: SortKey) -> Ordering {
+    fn none_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        }
+    }
+    let timestamp = |value: &Option<String>| value.as_deref().and_then(datetime::parse_timestamp);
+    match key {
+        SortKey::Name => a.file_name.cmp(&b.file_name),
+        SortKey::Size => none_last(a.file_size, b.file_size),
+        SortKey::Created => none_last(
+            timestamp(&a.creation_date_utc),
+            timestamp(&b.creation_date_utc),
+        ),
+        SortKey::Expiry => none_last(timestamp(&a.expires_at_utc), timestamp(&b.expires_at_utc)),
+    }
+}
+
+/// Formats the size in bytes as a human-readable string, e.g. `1.5 KiB`.
+pub fn human_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} {}", SIZE_UNITS[unit])
+    } else {
+        format!("{size:.1} {}", SIZE_UNITS[unit])
+    }
+}
+
+/// Formats the creation date relative to the given time, e.g. `2d ago`.
+fn format_created(value: Option<&str>, now: i64) -> String {
+    match value.map(|value| (value, datetime::parse_timestamp(value))) {
+        Some((_, Some(timestamp))) => format!("{} ago", datetime::format_duration(now - timestamp)),
+        Some((value, None)) => value.to_string(),
+        None => String::from("n/a"),
+    }
+}
+
+/// Formats the expiration date relative to the given time, e.g. `in 3h`.
+fn format_expiry(value: Option<&str>, now: i64) -> String {
+    match value.map(|value| (value, datetime::parse_timestamp(value))) {
+        Some((_, Some(timestamp))) if timestamp > now => {
+            format!("in {}", datetime::format_duration(timestamp - now))
+        }
+        Some((_, Some(_))) => String::from("expired"),
+        Some((value, None)) => value.to_string(),
+        None => String::from("never"),
+    }
+}
+
+/// Truncates the value to the given number of characters, marking the truncation with `…`.
+fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        value.to_string()
+    } else {
+        let mut truncated = value
+            .chars()
+            .take(width.saturating_sub(1))
+            .collect::<String>();
+        truncated.push('…');
+        truncated
+    }
+}
+
+/// Returns the values of the item in the order of [`COLUMNS`].
+///
+/// Missing values are empty.
+fn row(item: &ListItem) -> [String; 5] {
+    [
+        item.file_name.clone(),
+        item.file_size
+            .map(|size| size.to_string())
+            .unwrap_or_default(),
+        item.item_type.clone(),
+        item.creation_date_utc.clone().unwrap_or_default(),
+        item.expires_at_utc.clone().unwrap_or_default(),
+    ]
+}
+
+/// Returns the human-readable values of the item in the order of [`HEADERS`].
+///
+/// Times are shown relative to `now` (UNIX timestamp).
+pub fn table_row(item: &ListItem, now: i64) -> [String; 5] {
+    [
+        item.file_name.clone(),
+        item.file_size
+            .map(human_size)
+            .unwrap_or_else(|| String::from("n/a")),
+        item.item_type.clone(),
+        format_created(item.creation_date_utc.as_deref(), now),
+        format_expiry(item.expires_at_utc.as_deref(), now),
+    ]
+}
+
+/// Escapes a CSV field as described in RFC 4180.
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes a TSV field by replacing the backslashes, tabs and line breaks with escape sequences.
+fn escape_tsv(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Writes the items in the given export format.
+pub fn write_items<Output: Write>(
+    output: &mut Output,
+    items: &[ListItem],
+    format: ListFormat,
+) -> Result<()> {
+    match format {
+        ListFormat::Json => {
+            serde_json::to_writer(&mut *output, items)?;
+            writeln!(output)?;
+        }
+        ListFormat::Ndjson => {
+            for item in items {
+                serde_json::to_writer(&mut *output, item)?;
+                writeln!(output)?;
+            }
+        }
+        ListFormat::Csv | ListFormat::Tsv => {
+            let (separator, escape): (&str, fn(&str) -> String) = match format {
+                ListFormat::Csv => (",", escape_csv),
+                _ => ("\t", escape_tsv),
+            };
+            writeln!(output, "{}", COLUMNS.join(separator))?;
+            for item in items {
+                let row = row(item).map(|value| escape(&value));
+                writeln!(output, "{}", row.join(separator))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the items as a table, followed by the totals.
+///
+/// Times are shown relative to `now` (UNIX timestamp) and the file names are truncated
+/// if the table does not fit into `max_width`.
+pub fn write_table<Output: Write>(
+    output: &mut Output,
+    items: &[ListItem],
+    now: i64,
+    max_width: Option<usize>,
+) -> Result<()> {
+    if items.is_empty() {
+        writeln!(output, "No files on server :(")?;
+        return Ok(());
+    }
+    let rows = items
+        .iter()
+        .map(|item| table_row(item, now))
+        .collect::<Vec<_>>();
+    let mut widths = HEADERS.map(str::len);
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let table_width = widths.iter().sum::<usize>() + (widths.len() - 1) * 3;
+    if let Some(max_width) = max_width {
+        if table_width > max_width {
+            widths[0] = widths[0]
+                .saturating_sub(table_width - max_width)
+                .max(MIN_NAME_WIDTH);
+        }
+    }
+    let [name_width, size_width, type_width, created_width, expires_width] = widths;
+    writeln!(
+        output,
+        "{:^name_width$} | {:^size_width$} | {:^type_width$} | {:^created_width$} | {:^expires_width$}",
+        HEADERS[0], HEADERS[1], HEADERS[2], HEADERS[3], HEADERS[4]
+    )?;
+    writeln!(
+        output,
+        "{:-<name_width$}-|-{:->size_width$}-|-{:-<type_width$}-|-{:-<created_width$}-|-{:-<expires_width$}",
+        "", "", "", "", ""
+    )?;
+    for [name, size, item_type, created, expires] in &rows {
+        writeln!(
+            output,
+            "{:<name_width$} | {:>size_width$} | {:<type_width$} | {:<created_width$} | {}",
+            truncate(name, name_width),
+            size,
+            item_type,
+            created,
+            expires
+        )?;
+    }
+    let total_size = items.iter().filter_map(|item| item.file_size).sum::<u64>();
+    writeln!(
+        output,
+        "\n{} {}, {} in total",
+        items.len(),
+        if items.len() == 1 { "file" } else { "files" },
+        human_size(total_size)
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_792_368_000;
+
+    fn item(name: &str, size: Option<u64>, item_type: &str, expires: Option<&str>) -> ListItem {
+        ListItem {
+            file_name: name.to_string(),
+            file_size: size,
+            item_type: item_type.to_string(),
+            creation_date_utc: Some(String::from("2026-10-18T00:00:00Z")),
+            expires_at_utc: expires.map(String::from),
+        }
+    }
+
+    fn items() -> Vec<ListItem> {
+        vec![
+            item("b.txt", Some(2048), "file", Some("2026-10-19T03:00:00Z")),
+            item("a.log", Some(10), "oneshot", None),
+            item("c", None, "url", Some("2026-10-18T00:00:00Z")),
+        ]
+    }
+
+    fn names(items: &[ListItem]) -> Vec<&str> {
+        items.iter().map(|item| item.file_name.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_and_filter_items() {
+        let options = ListOptions {
+            sort: Some(SortKey::Size),
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["a.log", "b.txt", "c"], names(&options.apply(items())));
+
+        let options = ListOptions {
+            sort: Some(SortKey::Expiry),
+            reverse: true,
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["a.log", "b.txt", "c"], names(&options.apply(items())));
+
+        let options = ListOptions {
+            filters: vec![ItemFilter::parse("*.*").expect("filter should parse")],
+            sort: Some(SortKey::Name),
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["a.log", "b.txt"], names(&options.apply(items())));
+
+        let options = ListOptions {
+            filters: vec![ItemFilter::parse("oneshot").expect("filter should parse")],
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["a.log"], names(&options.apply(items())));
+
+        let options = ListOptions {
+            filters: vec![ItemFilter::names(&["*.log", "*.txt"]).expect("globs should parse")],
+            sort: Some(SortKey::Name),
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["a.log", "b.txt"], names(&options.apply(items())));
+
+        let options = ListOptions {
+            filters: ItemFilter::parse_all(&["*.log", "file", "*.txt"])
+                .expect("filters should parse"),
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["b.txt"], names(&options.apply(items())));
+
+        let options = ListOptions {
+            filters: ItemFilter::parse_all(&["file", "url"]).expect("filters should parse"),
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["b.txt", "c"], names(&options.apply(items())));
+
+        let options = ListOptions {
+            filters: ItemFilter::parse_all(&["oneshot", "*.txt", "url", "*.log"])
+                .expect("filters should parse"),
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["a.log"], names(&options.apply(items())));
+    }
+
+    #[test]
+    fn export_items_as_csv() {
+        let mut items = items();
+        items[0].file_name = String::from("with,comma \"quoted\".txt");
+        let mut output = Vec::new();
+        write_items(&mut output, &items[..2], ListFormat::Csv).expect("items should be written");
+
+        assert_eq!(
+            "\
+file_name,file_size,item_type,creation_date_utc,expires_at_utc
+\"with,comma \"\"quoted\"\".txt\",2048,file,2026-10-18T00:00:00Z,2026-10-19T03:00:00Z
+a.log,10,oneshot,2026-10-18T00:00:00Z,
+",
+            String::from_utf8_lossy(&output)
+        );
+    }
+
+    #[test]
+    fn export_items_as_tsv() {
+        let mut items = items();
+        items[2].file_name = String::from("tab\there");
+        let mut output = Vec::new();
+        write_items(&mut output, &items[2..], ListFormat::Tsv).expect("items should be written");
+
+        assert_eq!(
+            "\
+file_name\tfile_size\titem_type\tcreation_date_utc\texpires_at_utc
+tab\\there\t\turl\t2026-10-18T00:00:00Z\t2026-10-18T00:00:00Z
+",
+            String::from_utf8_lossy(&output)
+        );
+    }
+
+    #[test]
+    fn export_items_as_ndjson() {
+        let mut output = Vec::new();
+        write_items(&mut output, &items()[1..2], ListFormat::Ndjson)
+            .expect("items should be written");
+
+        assert_eq!(
+            r#"{"file_name":"a.log","file_size":10,"item_type":"oneshot","creation_date_utc":"2026-10-18T00:00:00Z","expires_at_utc":null}
+"#,
+            String::from_utf8_lossy(&output)
+        );
+    }
+
+    #[test]
+    fn filter_by_type_and_age() {
+        let mut items = items();
+        items[1].creation_date_utc = Some(String::from("2026-10-01T00:00:00Z"));
+        items[2].creation_date_utc = None;
+        let options = ListOptions {
+            filters: vec![ItemFilter::CreatedBefore(NOW - 7 * 86400)],
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["a.log"], names(&options.apply(items)));
+
+        let options = ListOptions {
+            filters: vec![ItemFilter::item_type("url").expect("type should be valid")],
+            ..ListOptions::default()
+        };
+        assert_eq!(vec!["c"], names(&options.apply(self::items())));
+        assert!(ItemFilter::item_type("*.log").is_err());
+    }
+
+    #[test]
+    fn parse_sort_keys() {
+        assert_eq!(Some(SortKey::Created), "created".parse().ok());
+        assert!("date".parse::<SortKey>().is_err());
+    }
+
+    #[test]
+    fn human_readable_sizes() {
+        assert_eq!("0 B", human_size(0));
+        assert_eq!("1023 B", human_size(1023));
+        assert_eq!("1.5 KiB", human_size(1536));
+        assert_eq!("3.0 MiB", human_size(3 * 1024 * 1024));
+    }
+
+    #[test]
+    fn write_items_as_table() {
+        let mut output = Vec::new();
+        write_table(&mut output, &items(), NOW, None).expect("table should be written");
+        let output = String::from_utf8(output).expect("table should be valid UTF-8");
+
+        assert_eq!(
+            "\
+Name  |  Size   |  Type   | Created | Expires
+------|---------|---------|---------|--------
+b.txt | 2.0 KiB | file    | 1d ago  | in 3h
+a.log |    10 B | oneshot | 1d ago  | never
+c     |     n/a | url     | 1d ago  | expired
+
+3 files, 2.0 KiB in total
+",
+            output
+        );
+    }
+
+    #[test]
+    fn truncate_long_file_names() {
+        let items = vec![item(
+            "a-very-long-file-name-that-does-not-fit.txt",
+            Some(1),
+            "file",
+            None,
+        )];
+        let mut output = Vec::new();
+        write_table(&mut output, &items, NOW, Some(50)).expect("table should be written");
+        let output = String::from_utf8(output).expect("table should be valid UTF-8");
+
+        assert!(output
+            .lines()
+            .nth(2)
+            .is_some_and(|line| line.starts_with("a-very-long-fil… |  1 B")));
+    }
+}
@@ -0,0 +1,82 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::env;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Environment variable for setting the log level.
+pub const LOG_ENV: &str = "RPASTE_LOG";
+
+/// Prefix of the log targets of this crate.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// Logger that writes the records to stderr along with the elapsed time.
+#[derive(Debug)]
+struct Logger {
+    /// Time of the first log record.
+    start: OnceLock<Instant>,
+}
+
+/// Global logger instance.
+static LOGGER: Logger = Logger {
+    start: OnceLock::new(),
+};
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // wire level traces of the dependencies (e.g. ureq) are not redacted
+        metadata.level() <= log::max_level()
+            && (metadata.level() < log::Level::Trace || metadata.target().starts_with(CRATE_TARGET))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let elapsed = self.start.get_or_init(Instant::now).elapsed();
+            eprintln!(
+                "[{:>8.3}s {:<5} {}] {}",
+                elapsed.as_secs_f64(),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Returns the log level for the given number of `--verbose` flags.
+///
+/// The level is read from [`LOG_ENV`] (e.g. `debug`) if no flags are given.
+pub fn level(verbosity: usize) -> LevelFilter {
+    match verbosity {
+        0 => env::var(LOG_ENV)
+            .ok()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(LevelFilter::Off),
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Initializes the logger with the given level.
+///
+/// Records are written to stderr so that the output on stdout stays intact.
+pub fn init(level: LevelFilter) {
+    if level != LevelFilter::Off && log::set_logger(&LOGGER).is_ok() {
+        LOGGER.start.get_or_init(Instant::now);
+        log::set_max_level(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_levels() {
+        assert_eq!(LevelFilter::Info, level(1));
+        assert_eq!(LevelFilter::Debug, level(2));
+        assert_eq!(LevelFilter::Trace, level(5));
+    }
+}
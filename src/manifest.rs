@@ -0,0 +1,273 @@
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::upload::{UploadResult, Uploaded, Uploader};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Batch manifest that lists the entries to upload.
+///
+/// TOML manifests list the entries as an array of `[[entry]]` tables, other files are parsed
+/// as JSON lines with one entry per line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Entries to upload.
+    #[serde(default)]
+    pub entry: Vec<ManifestEntry>,
+    /// Directory that the relative paths of the entries are resolved against.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+/// Entry of a batch manifest.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestEntry {
+    /// File to upload.
+    pub path: Option<String>,
+    /// URL to shorten.
+    pub url: Option<String>,
+    /// Remote URL to upload the file from.
+    pub remote: Option<String>,
+    /// Expiration time for the link.
+    pub expire: Option<String>,
+    /// Whether if the file will disappear after being viewed once.
+    pub oneshot: Option<bool>,
+    /// File name to use on the server.
+    pub filename: Option<String>,
+    /// Profile of the server to upload to.
+    pub profile: Option<String>,
+}
+
+/// Source of the uploaded data for a manifest entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntrySource<'a> {
+    /// File to upload.
+    Path(&'a str),
+    /// URL to shorten.
+    Url(&'a str),
+    /// Remote URL to upload the file from.
+    Remote(&'a str),
+}
+
+impl ManifestEntry {
+    /// Returns the source of the entry, which must be exactly one of `path`, `url` or `remote`.
+    fn source(&self) -> Result<EntrySource<'_>> {
+        match (&self.path, &self.url, &self.remote) {
+            (Some(path), None, None) => Ok(EntrySource::Path(path)),
+            (None, Some(url), None) => Ok(EntrySource::Url(url)),
+            (None, None, Some(remote)) => Ok(EntrySource::Remote(remote)),
+            _ => Err(Error::ManifestError(String::from(
+                "each entry must have exactly one of path, url or remote",
+            ))),
+        }
+    }
+
+    /// Returns the label of the entry to show in the results.
+    fn label(&self) -> &str {
+        self.path
+            .as_deref()
+            .or(self.url.as_deref())
+            .or(self.remote.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the configuration to use for uploading the entry.
+    ///
+    /// The tokens of the profile are read into `config` when it is used for the first time,
+    /// see [`Config::with_profile`].
+    pub fn config(&self, config: &mut Config) -> Result<Config> {
+        let mut config = match &self.profile {
+            Some(profile) => config.with_profile(profile)?,
+            None => config.clone(),
+        };
+        if let Some(oneshot) = self.oneshot {
+            config.paste.oneshot = Some(oneshot);
+        }
+        if self.expire.is_some() {
+            config.paste.expire = self.expire.clone();
+        }
+        if self.filename.is_some() {
+            config.paste.filename = self.filename.clone();
+        }
+        Ok(config)
+    }
+}
+
+impl Manifest {
+    /// Parses the manifest file at the given path.
+    ///
+    /// The relative paths of the entries are resolved against the directory of the manifest.
+    pub fn parse(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut manifest: Self = if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            toml::from_str(&contents)?
+        } else {
+            Self::parse_json_lines(&contents)?
+        };
+        for entry in &manifest.entry {
+            entry.source()?;
+        }
+        manifest.dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(manifest)
+    }
+
+    /// Parses entries from JSON lines, ignoring empty lines.
+    fn parse_json_lines(contents: &str) -> Result<Self> {
+        let entry = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self {
+            entry,
+            ..Self::default()
+        })
+    }
+
+    /// Uploads the entries one by one and returns the results in order.
+    ///
+    /// `configs` are the configurations of the entries as returned by [`ManifestEntry::config`].
+    pub fn upload<'a>(&'a self, configs: &'a [Config]) -> Vec<UploadResult<'a, Uploaded>> {
+        self.entry
+            .iter()
+            .zip(configs)
+            .map(|(entry, config)| {
+                let uploader = Uploader::new(config);
+                match entry.source() {
+                    Ok(EntrySource::Path(path)) => {
+                        let file = self.dir.join(path);
+                        uploader.upload_file_as(path, &file.to_string_lossy(), None)
+                    }
+                    Ok(EntrySource::Url(url)) => uploader.upload_url(url),
+                    Ok(EntrySource::Remote(remote)) => uploader.upload_remote_url(remote),
+                    Err(e) => UploadResult(entry.label(), Err(e)),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+
+    #[test]
+    fn parse_toml_manifest() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+                [[entry]]
+                path = "build.log"
+                expire = "1h"
+                filename = "first.log"
+
+                [[entry]]
+                url = "https://example.com"
+                oneshot = true
+                profile = "backup"
+            "#,
+        )
+        .expect("manifest should deserialize");
+
+        assert_eq!(2, manifest.entry.len());
+        assert_eq!(
+            Ok(EntrySource::Path("build.log")),
+            manifest.entry[0].source().map_err(|e| e.to_string())
+        );
+        assert_eq!(Some("first.log"), manifest.entry[0].filename.as_deref());
+        assert_eq!(
+            Ok(EntrySource::Url("https://example.com")),
+            manifest.entry[1].source().map_err(|e| e.to_string())
+        );
+        assert_eq!(Some("backup"), manifest.entry[1].profile.as_deref());
+    }
+
+    #[test]
+    fn parse_json_lines_manifest() {
+        let manifest = Manifest::parse_json_lines(
+            r#"{"path": "a.log", "oneshot": true}
+
+            {"remote": "https://example.com/file.txt", "expire": "10min"}
+            "#,
+        )
+        .expect("manifest should be parsed");
+
+        assert_eq!(2, manifest.entry.len());
+        assert_eq!(Some(true), manifest.entry[0].oneshot);
+        assert_eq!(
+            Ok(EntrySource::Remote("https://example.com/file.txt")),
+            manifest.entry[1].source().map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn paths_are_relative_to_manifest() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rpaste-manifest-test-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("uploads.jsonl");
+        let result = fs::write(&path, r#"{"path": "a.log"}"#)
+            .map_err(Error::from)
+            .and_then(|_| Manifest::parse(&path));
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(dir, result?.dir);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(Manifest::parse_json_lines(r#"{"path": "a.log", "expiry": "1h"}"#).is_err());
+    }
+
+    #[test]
+    fn entry_needs_exactly_one_source() {
+        let entry = ManifestEntry {
+            path: Some(String::from("a.log")),
+            url: Some(String::from("https://example.com")),
+            ..ManifestEntry::default()
+        };
+        assert!(entry.source().is_err());
+        assert!(ManifestEntry::default().source().is_err());
+    }
+
+    #[test]
+    fn entry_config_overrides_base_config() {
+        let mut config = Config::default();
+        config.server.address = String::from("https://paste.example.com");
+        config.paste.expire = Some(String::from("1d"));
+        config.profiles.insert(
+            String::from("backup"),
+            ServerConfig {
+                address: String::from("https://backup.example.com"),
+                ..ServerConfig::default()
+            },
+        );
+        let entry = ManifestEntry {
+            path: Some(String::from("a.log")),
+            oneshot: Some(true),
+            filename: Some(String::from("b.log")),
+            profile: Some(String::from("backup")),
+            ..ManifestEntry::default()
+        };
+
+        let entry_config = entry.config(&mut config).expect("profile should exist");
+        assert_eq!("https://backup.example.com", entry_config.server.address);
+        assert_eq!(Some(true), entry_config.paste.oneshot);
+        assert_eq!(Some("1d"), entry_config.paste.expire.as_deref());
+        assert_eq!(Some("b.log"), entry_config.paste.filename.as_deref());
+
+        let entry = ManifestEntry {
+            profile: Some(String::from("missing")),
+            ..entry
+        };
+        assert!(matches!(
+            entry.config(&mut config),
+            Err(Error::UnknownProfileError(profile)) if profile == "missing"
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::error::{Error, Result};
+use crate::failover::Failover;
+use crate::upload::{UploadResult, Uploaded, Uploader};
+
+/// Uploader that sends the same content to multiple servers.
+#[derive(Debug)]
+pub struct Mirror<'a> {
+    /// Uploaders of the servers, which fail over to their failover addresses.
+    uploaders: Vec<Failover<'a>>,
+    /// Number of servers that the upload needs to succeed on.
+    quorum: usize,
+}
+
+impl<'a> Mirror<'a> {
+    /// Constructs a new instance for the given servers.
+    ///
+    /// The quorum defaults to all of the servers.
+    pub fn new(uploaders: Vec<Failover<'a>>, quorum: Option<usize>) -> Result<Self> {
+        let quorum = quorum.unwrap_or(uploaders.len());
+        if quorum == 0 || quorum > uploaders.len() {
+            return Err(Error::UploadError(format!(
+                "quorum must be between 1 and {} (the number of servers)",
+                uploaders.len()
+            )));
+        }
+        Ok(Self { uploaders, quorum })
+    }
+
+    /// Returns `true` if the uploads are sent to more than one server.
+    pub fn is_mirrored(&self) -> bool {
+        self.uploaders.len() > 1
+    }
+
+    /// Returns the number of servers that the upload needs to succeed on.
+    pub fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    /// Runs the upload on each server and adds the results to `results` in the order of the
+    /// servers.
+    ///
+    /// The errors are prefixed with the server address if the upload is mirrored. Returns
+    /// `false` if the mirrored upload does not reach the quorum.
+    pub fn upload<F>(&self, results: &mut Vec<UploadResult<'a, Uploaded>>, upload: F) -> bool
+    where
+        F: Fn(&Uploader<'a>) -> UploadResult<'a, Uploaded>,
+    {
+        let uploads = self
+            .uploaders
+            .iter()
+            .map(|failover| {
+                let UploadResult(label, result) = failover.upload(&upload);
+                let result = match result {
+                    Err(e) if self.is_mirrored() => {
+                        Err(Error::UploadError(format!("{}: {e}", failover.address())))
+                    }
+                    result => result,
+                };
+                UploadResult(label, result)
+            })
+            .collect::<Vec<_>>();
+        let reached = !self.is_mirrored() || self.has_quorum(&uploads);
+        results.extend(uploads);
+        reached
+    }
+
+    /// Returns `true` if the results of an upload reach the quorum.
+    pub fn has_quorum(&self, results: &[UploadResult<'a, Uploaded>]) -> bool {
+        results.iter().filter(|result| result.1.is_ok()).count() >= self.quorum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn validate_quorum() {
+        let configs = vec![Config::default()];
+        let uploaders = || vec![Failover::new(&configs), Failover::new(&configs)];
+        assert_eq!(
+            2,
+            Mirror::new(uploaders(), None)
+                .map(|v| v.quorum())
+                .ok()
+                .unwrap_or_default()
+        );
+        assert!(Mirror::new(uploaders(), Some(1)).is_ok());
+        assert!(Mirror::new(uploaders(), Some(0)).is_err());
+        assert!(Mirror::new(uploaders(), Some(3)).is_err());
+    }
+
+    #[test]
+    fn count_quorum() -> Result<()> {
+        let configs = vec![Config::default()];
+        let uploaders = (0..3).map(|_| Failover::new(&configs)).collect();
+        let mirror = Mirror::new(uploaders, Some(2))?;
+        let result = |ok: bool| {
+            UploadResult(
+                "a.txt",
+                if ok {
+                    Ok(Uploaded::from(String::from(
+                        "https://paste.example.com/a.txt",
+                    )))
+                } else {
+                    Err(Error::UploadError(String::from("unavailable")))
+                },
+            )
+        };
+        assert!(mirror.has_quorum(&[result(true), result(false), result(true)]));
+        assert!(!mirror.has_quorum(&[result(true), result(false), result(false)]));
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use crate::checksum;
+use crate::datetime;
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Segment of a name template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Literal text.
+    Text(String),
+    /// File name without the extension.
+    Stem,
+    /// Extension of the file without the leading dot.
+    Ext,
+    /// Current date in `YYYY-MM-DD` format (UTC).
+    Date,
+    /// First 8 characters of the SHA-256 hash of the file contents.
+    Hash8,
+    /// Position of the file in the arguments, starting from 1.
+    Index,
+}
+
+/// Template for naming the uploaded files, e.g. `{stem}-{date}.{ext}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    /// Segments of the template.
+    segments: Vec<Segment>,
+}
+
+impl NameTemplate {
+    /// Parses the given template.
+    ///
+    /// Supported placeholders are `{stem}`, `{ext}`, `{date}`, `{hash8}` and `{index}`.
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start != 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or_else(|| {
+                Error::NameTemplateError(format!("unclosed placeholder in {template}"))
+            })? + start;
+            segments.push(match &rest[start + 1..end] {
+                "stem" => Segment::Stem,
+                "ext" => Segment::Ext,
+                "date" => Segment::Date,
+                "hash8" => Segment::Hash8,
+                "index" => Segment::Index,
+                placeholder => {
+                    return Err(Error::NameTemplateError(format!(
+                        "unknown placeholder: {{{placeholder}}}"
+                    )))
+                }
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Renders the name of the file at the given path and index.
+    ///
+    /// The file is only read if the template contains `{hash8}`.
+    pub fn render(&self, path: &str, index: usize) -> Result<String> {
+        let file = Path::new(path);
+        let mut name = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => name.push_str(text),
+                Segment::Stem => name.push_str(
+                    &file
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy())
+                        .unwrap_or_default(),
+                ),
+                Segment::Ext => name.push_str(
+                    &file
+                        .extension()
+                        .map(|extension| extension.to_string_lossy())
+                        .unwrap_or_default(),
+                ),
+                Segment::Date => name.push_str(&datetime::today()),
+                Segment::Hash8 => name.push_str(&checksum::hash(File::open(file)?)?[..8]),
+                Segment::Index => name.push_str(&index.to_string()),
+            }
+        }
+        Ok(name)
+    }
+}
+
+/// Splits a `local=remote` argument into the local path and the file name on the server.
+///
+/// Arguments that name an existing file are returned as-is.
+pub fn split_rename(arg: &str) -> (&str, Option<&str>) {
+    if Path::new(arg).exists() {
+        return (arg, None);
+    }
+    match arg.rsplit_once('=') {
+        Some((local, remote)) if !local.is_empty() && !remote.is_empty() => (local, Some(remote)),
+        _ => (arg, None),
+    }
+}
+
+/// Returns the names that occur more than once, in the order of their first occurrence.
+pub fn duplicate_names<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Vec<&'a str> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for name in names {
+        let count = counts.entry(name).or_default();
+        if *count == 0 {
+            order.push(name);
+        }
+        *count += 1;
+    }
+    order.retain(|name| counts[name] > 1);
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_name_template() {
+        let template =
+            NameTemplate::parse("{index}_{stem}-v2.{ext}").expect("template should parse");
+        assert_eq!(
+            "3_build-v2.log",
+            template
+                .render("logs/build.log", 3)
+                .expect("template should render")
+        );
+    }
+
+    #[test]
+    fn render_hash_placeholder() {
+        let template = NameTemplate::parse("{hash8}").expect("template should parse");
+        assert_eq!(
+            "d3f49f6e",
+            template
+                .render("tests/token_file_parsing/token.txt", 1)
+                .expect("template should render")
+        );
+    }
+
+    #[test]
+    fn invalid_name_templates() {
+        assert!(NameTemplate::parse("{stem").is_err());
+        assert!(NameTemplate::parse("{name}.txt").is_err());
+    }
+
+    #[test]
+    fn split_local_and_remote_names() {
+        assert_eq!(
+            ("a.log", Some("first.txt")),
+            split_rename("a.log=first.txt")
+        );
+        assert_eq!(("a.log", None), split_rename("a.log"));
+        assert_eq!(("Cargo.toml", None), split_rename("Cargo.toml"));
+        assert_eq!(("=x", None), split_rename("=x"));
+    }
+
+    #[test]
+    fn find_duplicate_names() {
+        assert_eq!(
+            vec!["report.txt"],
+            duplicate_names(["report.txt", "a.log", "report.txt"])
+        );
+        assert!(duplicate_names(["a", "b"]).is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+use crate::error::{Error, Result};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+/// Maximum number of local files to pick from.
+const MAX_FILES: usize = 10000;
+
+/// Help text shown in the footer.
+const HELP: &str = "tab select | ctrl-a select all | enter accept | esc cancel";
+
+/// Fuzzy finder for picking one or more of the candidates.
+#[derive(Debug)]
+struct Picker {
+    /// Candidates to pick from.
+    candidates: Vec<String>,
+    /// Search query.
+    query: String,
+    /// Indices of the candidates matching the query along with the indices of the matched
+    /// characters, best matches first.
+    matches: Vec<(usize, Vec<usize>)>,
+    /// Indices of the selected candidates.
+    selected: BTreeSet<usize>,
+    /// State of the list (i.e. the highlighted match).
+    list: ListState,
+}
+
+impl Picker {
+    /// Constructs a new instance.
+    fn new(candidates: Vec<String>) -> Self {
+        let mut picker = Self {
+            candidates,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: BTreeSet::new(),
+            list: ListState::default(),
+        };
+        picker.update_matches();
+        picker
+    }
+
+    /// Matches the candidates against the query and highlights the best match.
+    fn update_matches(&mut self) {
+        let matcher = SkimMatcherV2::default();
+        let mut matches = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, candidate)| {
+                matcher
+                    .fuzzy_indices(candidate, &self.query)
+                    .map(|(score, indices)| (score, index, indices))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = matches
+            .into_iter()
+            .map(|(_, index, indices)| (index, indices))
+            .collect();
+        self.list.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    /// Returns the index of the highlighted candidate.
+    fn current(&self) -> Option<usize> {
+        self.list
+            .selected()
+            .and_then(|index| self.matches.get(index))
+            .map(|(index, _)| *index)
+    }
+
+    /// Moves the highlight by the given number of rows.
+    fn move_by(&mut self, rows: isize) {
+        if let Some(index) = self.list.selected() {
+            let last = self.matches.len().saturating_sub(1);
+            self.list
+                .select(Some(index.saturating_add_signed(rows).min(last)));
+        }
+    }
+
+    /// Handles the key press.
+    ///
+    /// Returns the picked candidates once the selection is accepted (empty if cancelled).
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Vec<String>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(Vec::new()),
+            KeyCode::Char('c') if ctrl => return Some(Vec::new()),
+            KeyCode::Enter => {
+                let mut picked = self.selected.clone();
+                if picked.is_empty() {
+                    picked.extend(self.current());
+                }
+                return Some(
+                    picked
+                        .into_iter()
+                        .map(|index| self.candidates[index].clone())
+                        .collect(),
+                );
+            }
+            KeyCode::Tab => {
+                if let Some(index) = self.current() {
+                    if !self.selected.remove(&index) {
+                        self.selected.insert(index);
+                    }
+                    self.move_by(1);
+                }
+            }
+            KeyCode::Char('a') if ctrl => {
+                self.selected
+                    .extend(self.matches.iter().map(|(index, _)| *index));
+            }
+            KeyCode::Down => self.move_by(1),
+            KeyCode::Char('n') if ctrl => self.move_by(1),
+            KeyCode::Up => self.move_by(-1),
+            KeyCode::Char('p') if ctrl => self.move_by(-1),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Draws the picker.
+    fn draw(&mut self, frame: &mut Frame, prompt: &str) {
+        let [query_area, list_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        frame.render_widget(
+            Line::from(vec![
+                Span::from(format!("{prompt} > ")).bold(),
+                Span::from(self.query.as_str()),
+                Span::from("_"),
+            ]),
+            query_area,
+        );
+        let items = self.matches.iter().map(|(index, indices)| {
+            let mark = if self.selected.contains(index) {
+                "● "
+            } else {
+                "  "
+            };
+            let mut spans = vec![Span::from(mark).fg(Color::Green)];
+            spans.extend(self.candidates[*index].chars().enumerate().map(|(i, c)| {
+                let span = Span::from(c.to_string());
+                if indices.contains(&i) {
+                    span.fg(Color::Yellow).bold()
+                } else {
+                    span
+                }
+            }));
+            ListItem::new(Line::from(spans))
+        });
+        frame.render_stateful_widget(
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list_area,
+            &mut self.list,
+        );
+        frame.render_widget(
+            Line::from(format!(
+                " {}/{} ({} selected) | {HELP}",
+                self.matches.len(),
+                self.candidates.len(),
+                self.selected.len()
+            ))
+            .fg(Color::DarkGray),
+            footer_area,
+        );
+    }
+
+    /// Runs the event loop until the selection is accepted or cancelled.
+    fn run(&mut self, terminal: &mut DefaultTerminal, prompt: &str) -> Result<Vec<String>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame, prompt))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    if let Some(picked) = self.handle_key(key) {
+                        return Ok(picked);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the files in the given directories (recursively), skipping the hidden entries.
+///
+/// The current directory is used if no directories are given. At most [`MAX_FILES`] files
+/// are returned, and a warning is shown if the rest are left out.
+pub fn local_files(dirs: &[String]) -> Result<Vec<String>> {
+    fn walk(dir: &Path, files: &mut Vec<String>) -> Result<()> {
+        let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            if files.len() > MAX_FILES {
+                break;
+            }
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                walk(&path, files)?;
+            } else {
+                let path = path.strip_prefix("./").unwrap_or(&path);
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    if dirs.is_empty() {
+        walk(Path::new("."), &mut files)?;
+    }
+    for dir in dirs {
+        if Path::new(dir).is_dir() {
+            walk(Path::new(dir), &mut files)?;
+        } else {
+            files.push(dir.to_string());
+        }
+    }
+    if files.len() > MAX_FILES {
+        eprintln!("Warning: only the first {MAX_FILES} files are listed");
+        files.truncate(MAX_FILES);
+    }
+    Ok(files)
+}
+
+/// Opens a fuzzy finder on the terminal and returns the picked candidates.
+///
+/// Returns an empty list if the selection is cancelled.
+pub fn pick(candidates: Vec<String>, prompt: &str) -> Result<Vec<String>> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(Error::PickError(String::from(
+            "picking files requires a terminal",
+        )));
+    }
+    let mut picker = Picker::new(candidates);
+    let mut terminal = ratatui::try_init()?;
+    let result = picker.run(&mut terminal, prompt);
+    ratatui::try_restore()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(picker: &mut Picker, code: KeyCode) -> Option<Vec<String>> {
+        picker.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn picker() -> Picker {
+        Picker::new(
+            ["src/lib.rs", "src/upload.rs", "README.md"]
+                .map(String::from)
+                .to_vec(),
+        )
+    }
+
+    #[test]
+    fn pick_best_match() {
+        let mut picker = picker();
+        for c in "uplrs".chars() {
+            press(&mut picker, KeyCode::Char(c));
+        }
+        assert_eq!(1, picker.matches.len());
+        assert_eq!(
+            Some(vec![String::from("src/upload.rs")]),
+            press(&mut picker, KeyCode::Enter)
+        );
+    }
+
+    #[test]
+    fn pick_multiple_candidates() {
+        let mut picker = picker();
+        press(&mut picker, KeyCode::Tab);
+        press(&mut picker, KeyCode::Tab);
+        assert_eq!(
+            Some(vec![
+                String::from("src/lib.rs"),
+                String::from("src/upload.rs")
+            ]),
+            press(&mut picker, KeyCode::Enter)
+        );
+        assert_eq!(Some(Vec::new()), press(&mut picker, KeyCode::Esc));
+    }
+
+    #[test]
+    fn list_local_files() {
+        let files = local_files(&[String::from("tests/token_file_parsing")])
+            .expect("directory should be listed");
+        assert!(files.contains(&String::from("tests/token_file_parsing/token.txt")));
+    }
+}
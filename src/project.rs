@@ -0,0 +1,305 @@
+use crate::checksum;
+use crate::config::ConfigLayer;
+use crate::error::Result;
+use crate::prompt;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Name of the project-local configuration file.
+pub const PROJECT_CONFIG_FILE: &str = ".rpaste.toml";
+
+/// Tables of the project configuration that are applied without being trusted.
+///
+/// The other settings (e.g. the server address or the token commands) could redirect the
+/// uploads or run commands, so they are only applied from the trusted files.
+const SAFE_TABLES: &[&str] = &["paste", "style"];
+
+/// Settings of the [`SAFE_TABLES`] that are only applied from the trusted files, as `(table,
+/// key)` pairs.
+///
+/// The mirrors are profiles of the user configuration, so they would send the uploads to the
+/// servers of the other profiles along with their tokens.
+const TRUSTED_SETTINGS: &[(&str, &str)] = &[("paste", "mirrors")];
+
+/// Returns `true` if the setting with the given key in the given table is applied without the
+/// file being trusted.
+fn is_safe(table: &str, key: &str) -> bool {
+    SAFE_TABLES.contains(&table) && !TRUSTED_SETTINGS.contains(&(table, key))
+}
+
+/// Removes the settings that require the file to be trusted from the table.
+fn strip_untrusted(table: &mut toml::Table) {
+    table.retain(|name, _| SAFE_TABLES.contains(&name));
+    for (name, value) in table.iter_mut() {
+        if let toml::Value::Table(settings) = value {
+            settings.retain(|key, _| is_safe(name, key));
+        }
+    }
+}
+
+/// Returns the project configuration file in the given directory or its closest ancestor.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Returns the settings that require the file to be trusted, as `key = value` lines.
+fn untrusted_settings(table: &toml::Table) -> Vec<String> {
+    fn collect(prefix: &str, table: &toml::Table, settings: &mut Vec<String>) {
+        for (key, value) in table {
+            let key = if prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("{prefix}.{key}")
+            };
+            match value {
+                toml::Value::Table(table) => collect(&key, table, settings),
+                value => settings.push(format!("{key} = {value}")),
+            }
+        }
+    }
+    let table = table
+        .iter()
+        .filter_map(|(name, value)| match value {
+            toml::Value::Table(settings) if SAFE_TABLES.contains(&name.as_str()) => {
+                let settings = settings
+                    .iter()
+                    .filter(|(key, _)| !is_safe(name, key))
+                    .map(|(key, value)| (key.to_string(), value.clone()))
+                    .collect::<toml::Table>();
+                (!settings.is_empty()).then(|| (name.to_string(), toml::Value::Table(settings)))
+            }
+            _ if SAFE_TABLES.contains(&name.as_str()) => None,
+            value => Some((name.to_string(), value.clone())),
+        })
+        .collect();
+    let mut settings = Vec::new();
+    collect("", &table, &mut settings);
+    settings
+}
+
+/// List of the trusted project configuration files along with the hashes of their contents.
+///
+/// A file needs to be trusted again after it is changed.
+#[derive(Debug)]
+pub struct TrustStore {
+    /// Path of the list.
+    path: PathBuf,
+}
+
+impl TrustStore {
+    /// Constructs a new instance.
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Returns the entry of the given file in the list.
+    fn entry(path: &Path, contents: &str) -> Result<String> {
+        let hash = checksum::hash(contents.as_bytes())?;
+        Ok(format!("{hash} {}", fs::canonicalize(path)?.display()))
+    }
+
+    /// Returns the entries in the list.
+    fn entries(&self) -> Result<Vec<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(contents.lines().map(String::from).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns whether if the file with the given contents is trusted.
+    pub fn is_trusted(&self, path: &Path, contents: &str) -> Result<bool> {
+        let entry = Self::entry(path, contents)?;
+        Ok(self.entries()?.contains(&entry))
+    }
+
+    /// Trusts the file with the given contents, replacing the previous entry of the file.
+    pub fn trust(&self, path: &Path, contents: &str) -> Result<()> {
+        let entry = Self::entry(path, contents)?;
+        let path = entry.split_once(' ').map(|v| v.1).unwrap_or_default();
+        let mut entries = self.entries()?;
+        entries.retain(|v| v.split_once(' ').map(|v| v.1) != Some(path));
+        entries.push(entry);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, entries.join("\n") + "\n")?;
+        Ok(())
+    }
+}
+
+/// Reads the project configuration file at the given path along with the files that it
+/// includes.
+///
+/// Returns the layers and the contents to trust, i.e. the paths and the settings of all the
+/// files, so that changing an included file requires the project to be trusted again.
+fn read(path: &Path) -> Result<(Vec<ConfigLayer>, String)> {
+    let mut layers = Vec::new();
+    ConfigLayer::read(path)?.expand(&mut layers, &mut Vec::new())?;
+    let contents = layers
+        .iter()
+        .map(|layer| format!("# {}\n{}\n", layer.path.display(), layer.table))
+        .collect();
+    Ok((layers, contents))
+}
+
+/// Trusts the project configuration file at the given path along with the files that it
+/// includes.
+pub fn trust(path: &Path, store: &TrustStore) -> Result<()> {
+    let (_, contents) = read(path)?;
+    store.trust(path, &contents)
+}
+
+/// Reads the project configuration file at the given path along with the files that it
+/// includes.
+///
+/// If the files are not trusted, the user is asked to trust them on the terminal. Only the
+/// [`SAFE_TABLES`] (except the [`TRUSTED_SETTINGS`]) are applied from the files that are not
+/// trusted.
+pub fn load(path: &Path, store: &TrustStore) -> Result<Vec<ConfigLayer>> {
+    let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+    load_with(path, store, interactive, |settings| {
+        eprintln!("The project configuration {} sets:", path.display());
+        for setting in settings {
+            eprintln!("  {setting}");
+        }
+        prompt::confirm("Trust this file?")
+    })
+}
+
+/// Reads the project configuration file like [`load`].
+///
+/// `ask` is called with the settings that require trust if the files are not trusted and
+/// `interactive` is set, and returns whether the user trusts the files.
+fn load_with<F>(
+    path: &Path,
+    store: &TrustStore,
+    interactive: bool,
+    ask: F,
+) -> Result<Vec<ConfigLayer>>
+where
+    F: FnOnce(&[String]) -> Result<bool>,
+{
+    let (mut layers, contents) = read(path)?;
+    let settings = layers
+        .iter()
+        .flat_map(|layer| untrusted_settings(&layer.table))
+        .collect::<Vec<_>>();
+    if settings.is_empty() || store.is_trusted(path, &contents)? {
+        return Ok(layers);
+    }
+    if interactive {
+        if ask(&settings)? {
+            store.trust(path, &contents)?;
+            return Ok(layers);
+        }
+    } else {
+        eprintln!(
+            "Warning: ignoring the server settings of the untrusted {}, run `rpaste config trust` to trust it",
+            path.display()
+        );
+    }
+    for layer in &mut layers {
+        strip_untrusted(&mut layer.table);
+    }
+    Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn detect_untrusted_settings() -> Result<()> {
+        let table = toml::from_str(
+            r#"
+            [server]
+            address = "https://team.example.com"
+            auth_token_command = "curl https://example.com"
+
+            [paste]
+            expire = "1h"
+            mirrors = ["work"]
+            "#,
+        )?;
+        assert_eq!(
+            vec![
+                r#"paste.mirrors = ["work"]"#,
+                r#"server.address = "https://team.example.com""#,
+                r#"server.auth_token_command = "curl https://example.com""#,
+            ],
+            untrusted_settings(&table)
+        );
+
+        let mut table = table;
+        strip_untrusted(&mut table);
+        assert_eq!("[paste]\nexpire = \"1h\"\n", table.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn find_and_trust_project_config() -> Result<()> {
+        let dir = env::temp_dir().join(format!("rpaste-project-test-{}", std::process::id()));
+        let nested = dir.join("src").join("nested");
+        fs::create_dir_all(&nested)?;
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        fs::write(&path, "[server]\naddress = \"https://team.example.com\"\n")?;
+        assert_eq!(Some(path.clone()), find(&nested));
+
+        let store = TrustStore::new(dir.join("trusted"));
+        let trusted = store.is_trusted(&path, "a").and_then(|trusted| {
+            store.trust(&path, "a")?;
+            Ok((
+                trusted,
+                store.is_trusted(&path, "a")?,
+                store.is_trusted(&path, "b")?,
+            ))
+        });
+        fs::remove_dir_all(&dir)?;
+        assert_eq!((false, true, false), trusted?);
+        Ok(())
+    }
+
+    #[test]
+    fn changed_includes_need_trust() -> Result<()> {
+        let dir = env::temp_dir().join(format!("rpaste-include-test-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        fs::write(&path, "include = [\"team.toml\"]\n")?;
+        fs::write(dir.join("team.toml"), "[paste]\nexpire = \"1h\"\n")?;
+        let store = TrustStore::new(dir.join("trusted"));
+        let trusted = trust(&path, &store).and_then(|_| {
+            let trusted = store.is_trusted(&path, &read(&path)?.1)?;
+            fs::write(
+                dir.join("team.toml"),
+                "[server]\naddress = \"https://evil.example.com\"\n",
+            )?;
+            let has_server = |layers: Vec<ConfigLayer>| {
+                layers
+                    .iter()
+                    .any(|layer| layer.table.contains_key("server"))
+            };
+            let ignored = has_server(load_with(&path, &store, false, |_| Ok(true))?);
+            let declined = has_server(load_with(&path, &store, true, |_| Ok(false))?);
+            let retrusted = store.is_trusted(&path, &read(&path)?.1)?;
+            let accepted = has_server(load_with(&path, &store, true, |settings| {
+                Ok(settings == [r#"server.address = "https://evil.example.com""#])
+            })?);
+            Ok((
+                trusted,
+                ignored,
+                declined,
+                retrusted,
+                accepted,
+                store.is_trusted(&path, &read(&path)?.1)?,
+            ))
+        });
+        fs::remove_dir_all(&dir)?;
+        assert_eq!((true, false, false, false, true, true), trusted?);
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use crate::error::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal;
+use std::io::{self, Write};
+
+/// Asks the user for confirmation on the terminal.
+pub fn confirm(prompt: &str) -> Result<bool> {
+    eprint!("{prompt} [y/N] ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Asks the user for a line of input on the terminal.
+///
+/// Leading and trailing whitespace will be trimmed.
+pub fn input(prompt: &str) -> Result<String> {
+    eprint!("{prompt}: ");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+/// Asks the user for a secret (e.g. a token) on the terminal without echoing the input.
+///
+/// Leading and trailing whitespace will be trimmed.
+pub fn secret(prompt: &str) -> Result<String> {
+    eprint!("{prompt}: ");
+    io::stderr().flush()?;
+    terminal::enable_raw_mode()?;
+    let result = read_secret();
+    terminal::disable_raw_mode()?;
+    eprintln!();
+    result
+}
+
+/// Reads the keys until enter is pressed, the terminal must be in raw mode.
+fn read_secret() -> Result<String> {
+    let mut secret = String::new();
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Enter => return Ok(secret.trim().to_string()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Err(io::Error::from(io::ErrorKind::Interrupted).into());
+                }
+                KeyCode::Backspace => {
+                    secret.pop();
+                }
+                KeyCode::Char(c) => secret.push(c),
+                _ => {}
+            }
+        }
+    }
+}
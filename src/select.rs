@@ -0,0 +1,364 @@
+use crate::error::{Error, Result};
+use regex::bytes::Regex;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, Read, Result as IoResult};
+use std::path::Path;
+
+/// Separator that is written between non-adjacent groups of matched lines.
+const GROUP_SEPARATOR: &[u8] = b"--\n";
+
+/// Inclusive range of line numbers, starting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    /// First line of the range.
+    pub start: usize,
+    /// Last line of the range, or `None` for reading until the end of the file.
+    pub end: Option<usize>,
+}
+
+impl LineRange {
+    /// Parses a range specification such as `1200-1350`, `1200-` or `42`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || Error::LineRangeError(format!("invalid line range: {spec}"));
+        let parse_line = |line: &str| line.parse::<usize>().map_err(|_| invalid());
+        let range = match spec.split_once('-') {
+            Some((start, "")) => Self {
+                start: parse_line(start)?,
+                end: None,
+            },
+            Some((start, end)) => Self {
+                start: parse_line(start)?,
+                end: Some(parse_line(end)?),
+            },
+            None => {
+                let line = parse_line(spec)?;
+                Self {
+                    start: line,
+                    end: Some(line),
+                }
+            }
+        };
+        if range.start == 0 || range.end.is_some_and(|end| end < range.start) {
+            return Err(invalid());
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for LineRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}-{}", self.start, end),
+            None => write!(f, "{}-end", self.start),
+        }
+    }
+}
+
+/// Pattern for selecting lines, along with the number of context lines to include.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    /// Regular expression that the selected lines match.
+    pub regex: Regex,
+    /// Number of lines to include before and after each matching line.
+    pub context: usize,
+}
+
+/// Selection of lines to upload from a file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Selection<'a> {
+    /// Range of lines to select.
+    pub range: Option<LineRange>,
+    /// Pattern to select lines with.
+    pub pattern: Option<&'a Pattern>,
+}
+
+impl<'a> Selection<'a> {
+    /// Returns `true` if the selection does not filter any lines.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_none() && self.pattern.is_none()
+    }
+
+    /// Returns the file name for the selection by appending a suffix to the original file name.
+    ///
+    /// e.g. `build.log` becomes `build_L1200-1350.log`
+    pub fn file_name(&self, path: &str) -> String {
+        let path = Path::new(path);
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_else(|| "file".into());
+        let mut suffix = Vec::new();
+        if let Some(range) = self.range {
+            suffix.push(format!("L{range}"));
+        }
+        if self.pattern.is_some() {
+            suffix.push(String::from("grep"));
+        }
+        let suffix = suffix.join("_");
+        match path.extension() {
+            Some(extension) => format!("{stem}_{suffix}.{}", extension.to_string_lossy()),
+            None => format!("{stem}_{suffix}"),
+        }
+    }
+
+    /// Wraps the given reader so that only the selected lines are read from it.
+    pub fn apply<R: BufRead>(self, reader: R) -> SelectedLines<'a, R> {
+        SelectedLines {
+            inner: reader,
+            selection: self,
+            line_number: 0,
+            buffer: Vec::new(),
+            position: 0,
+            before: VecDeque::new(),
+            after: 0,
+            last_written: None,
+            done: false,
+        }
+    }
+}
+
+/// Splits a `path:range` argument into the path and the line range.
+///
+/// Arguments that name an existing file or do not end with a range are returned as-is.
+pub fn split_line_range(arg: &str) -> Result<(&str, Option<LineRange>)> {
+    if Path::new(arg).exists() {
+        return Ok((arg, None));
+    }
+    match arg.rsplit_once(':') {
+        Some((path, spec))
+            if !path.is_empty()
+                && !spec.is_empty()
+                && spec.chars().all(|c| c.is_ascii_digit() || c == '-') =>
+        {
+            Ok((path, Some(LineRange::parse(spec)?)))
+        }
+        _ => Ok((arg, None)),
+    }
+}
+
+/// Reader that yields the selected lines of the inner reader.
+///
+/// Lines are read one at a time, so the whole input is never loaded into memory.
+#[derive(Debug)]
+pub struct SelectedLines<'a, R: BufRead> {
+    /// Inner reader.
+    inner: R,
+    /// Lines to select.
+    selection: Selection<'a>,
+    /// Number of the last line read from the inner reader.
+    line_number: usize,
+    /// Selected lines that are not consumed yet.
+    buffer: Vec<u8>,
+    /// Position of the next byte to consume in the buffer.
+    position: usize,
+    /// Lines that are kept as the leading context of the next match.
+    before: VecDeque<Vec<u8>>,
+    /// Number of trailing context lines that are still to be written.
+    after: usize,
+    /// Number of the last selected line.
+    last_written: Option<usize>,
+    /// Whether if there are no more lines to select.
+    done: bool,
+}
+
+impl<R: BufRead> SelectedLines<'_, R> {
+    /// Reads lines until at least one of them is selected or the input ends.
+    fn fill_buffer(&mut self) -> IoResult<()> {
+        self.buffer.clear();
+        self.position = 0;
+        while self.buffer.is_empty() && !self.done {
+            let mut line = Vec::new();
+            if self.inner.read_until(b'\n', &mut line)? == 0 {
+                self.done = true;
+                break;
+            }
+            self.line_number += 1;
+            if let Some(range) = self.selection.range {
+                if self.line_number < range.start {
+                    continue;
+                }
+                if range.end.is_some_and(|end| self.line_number > end) {
+                    self.done = true;
+                    break;
+                }
+            }
+            let Some(pattern) = self.selection.pattern else {
+                self.buffer.extend(line);
+                continue;
+            };
+            let content = line.strip_suffix(b"\n").unwrap_or(&line);
+            let content = content.strip_suffix(b"\r").unwrap_or(content);
+            if pattern.regex.is_match(content) {
+                let first = self.line_number - self.before.len();
+                if self.last_written.is_some_and(|last| last + 1 < first) {
+                    self.buffer.extend_from_slice(GROUP_SEPARATOR);
+                }
+                self.buffer.extend(self.before.drain(..).flatten());
+                self.buffer.extend(line);
+                self.after = pattern.context;
+                self.last_written = Some(self.line_number);
+            } else if self.after > 0 {
+                self.buffer.extend(line);
+                self.after -= 1;
+                self.last_written = Some(self.line_number);
+            } else if pattern.context > 0 {
+                self.before.push_back(line);
+                if self.before.len() > pattern.context {
+                    self.before.pop_front();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for SelectedLines<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        if self.position == self.buffer.len() {
+            self.fill_buffer()?;
+        }
+        let available = &self.buffer[self.position..];
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+
+    fn select(selection: Selection) -> String {
+        let mut output = String::new();
+        selection
+            .apply(INPUT.as_bytes())
+            .read_to_string(&mut output)
+            .expect("selected lines should be read");
+        output
+    }
+
+    fn pattern(regex: &str, context: usize) -> Pattern {
+        Pattern {
+            regex: Regex::new(regex).expect("regex should compile"),
+            context,
+        }
+    }
+
+    #[test]
+    fn parse_line_ranges() {
+        assert_eq!(
+            LineRange::parse("1200-1350").ok(),
+            Some(LineRange {
+                start: 1200,
+                end: Some(1350)
+            })
+        );
+        assert_eq!(
+            LineRange::parse("7-").ok(),
+            Some(LineRange {
+                start: 7,
+                end: None
+            })
+        );
+        assert_eq!(
+            LineRange::parse("42").ok(),
+            Some(LineRange {
+                start: 42,
+                end: Some(42)
+            })
+        );
+        for spec in ["0-2", "5-3", "-3", "1-2-3", ""] {
+            assert!(LineRange::parse(spec).is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn split_path_and_range() {
+        assert_eq!(
+            split_line_range("build.log:2-4").ok(),
+            Some((
+                "build.log",
+                Some(LineRange {
+                    start: 2,
+                    end: Some(4)
+                })
+            ))
+        );
+        assert_eq!(
+            split_line_range("build.log").ok(),
+            Some(("build.log", None))
+        );
+        assert_eq!(
+            split_line_range("http://example.com").ok(),
+            Some(("http://example.com", None))
+        );
+        assert!(split_line_range("build.log:4-2").is_err());
+    }
+
+    #[test]
+    fn select_line_range() {
+        let selection = Selection {
+            range: Some(LineRange {
+                start: 2,
+                end: Some(4),
+            }),
+            pattern: None,
+        };
+        assert_eq!("two\nthree\nfour\n", select(selection));
+    }
+
+    #[test]
+    fn select_pattern_with_context() {
+        let pattern = pattern("^(three|nine)$", 1);
+        let selection = Selection {
+            range: None,
+            pattern: Some(&pattern),
+        };
+        assert_eq!(
+            "two\nthree\nfour\n--\neight\nnine\nten\n",
+            select(selection)
+        );
+    }
+
+    #[test]
+    fn select_pattern_within_range() {
+        let pattern = pattern("e", 0);
+        let selection = Selection {
+            range: Some(LineRange {
+                start: 4,
+                end: None,
+            }),
+            pattern: Some(&pattern),
+        };
+        assert_eq!("five\n--\nseven\neight\nnine\nten\n", select(selection));
+    }
+
+    #[test]
+    fn selection_file_name() {
+        let pattern = pattern("error", 2);
+        let range = Some(LineRange {
+            start: 1200,
+            end: Some(1350),
+        });
+        assert_eq!(
+            "build_L1200-1350.log",
+            Selection {
+                range,
+                pattern: None
+            }
+            .file_name("logs/build.log")
+        );
+        assert_eq!(
+            "build_L1200-1350_grep",
+            Selection {
+                range,
+                pattern: Some(&pattern)
+            }
+            .file_name("build")
+        );
+    }
+}
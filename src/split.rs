@@ -0,0 +1,349 @@
+use crate::checksum::{Hasher, HashingReader};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::upload::{UploadResult, Uploaded, Uploader};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use url::Url;
+
+/// Suffix of the file name of the uploaded split manifests.
+const MANIFEST_SUFFIX: &str = ".parts.toml";
+
+/// Parses a size such as `1048576`, `512K`, `100MB` or `1GiB` into bytes.
+///
+/// The `K`, `M` and `G` units (optionally followed by `B`) are decimal, the `KiB`, `MiB` and
+/// `GiB` units are binary.
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let index = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(index);
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return None,
+    };
+    number
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(multiplier)
+        .filter(|size| *size > 0)
+}
+
+/// Manifest that lists the parts of a split file.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SplitManifest {
+    /// Name of the file.
+    pub name: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Parts of the file, in order.
+    #[serde(default)]
+    pub part: Vec<Part>,
+}
+
+/// Uploaded part of a split file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Part {
+    /// URL of the part.
+    pub url: String,
+    /// Size of the part in bytes.
+    pub size: u64,
+    /// Hex encoded SHA-256 digest of the part.
+    pub sha256: String,
+}
+
+/// Uploader of the files in parts.
+#[derive(Debug)]
+pub struct Splitter<'a> {
+    /// Uploader of the parts and the manifest.
+    uploader: &'a Uploader<'a>,
+    /// Maximum size of a part in bytes.
+    part_size: u64,
+    /// Number of parts to upload in parallel.
+    jobs: usize,
+}
+
+impl<'a> Splitter<'a> {
+    /// Constructs a new instance.
+    pub fn new(uploader: &'a Uploader<'a>, part_size: u64, jobs: usize) -> Self {
+        Self {
+            uploader,
+            part_size,
+            jobs: jobs.max(1),
+        }
+    }
+
+    /// Uploads the given file in parts, then uploads the manifest of the parts.
+    ///
+    /// The parts are named after the file with their numbers as the extension (e.g.
+    /// `trace.bin.001`). Returns the URL of the manifest.
+    pub fn upload_file<'f>(&self, file: &'f str) -> UploadResult<'f, Uploaded> {
+        UploadResult(file, self.upload(file))
+    }
+
+    /// Uploads the given file in parts and returns the URL of the manifest.
+    fn upload(&self, file: &str) -> Result<Uploaded> {
+        let size = fs::metadata(file)?.len();
+        let name = Path::new(file)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| Error::SplitError(format!("{file} is not a file")))?;
+        let count = usize::try_from(size.div_ceil(self.part_size).max(1))
+            .map_err(|_| Error::SplitError(format!("{file} has too many parts")))?;
+        let next = AtomicUsize::new(0);
+        // the remaining parts are not uploaded after a part fails
+        let failed = AtomicBool::new(false);
+        let parts = Mutex::new((0..count).map(|_| None).collect::<Vec<_>>());
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.min(count) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= count || failed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let part = self.upload_part(file, &name, index, count);
+                    if part.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    if let Ok(mut parts) = parts.lock() {
+                        parts[index] = Some(part);
+                    }
+                });
+            }
+        });
+        let parts = parts
+            .into_inner()
+            .map_err(|_| Error::SplitError(String::from("an upload thread panicked")))?;
+        let mut part = Vec::new();
+        let mut error = None;
+        for result in parts.into_iter().flatten() {
+            match result {
+                Ok(uploaded) => part.push(uploaded),
+                Err(e) => error = error.or(Some(e)),
+            }
+        }
+        if let Some(e) = error {
+            self.delete_parts(&part);
+            return Err(e);
+        }
+        if part.len() != count {
+            self.delete_parts(&part);
+            return Err(Error::SplitError(String::from("a part is not uploaded")));
+        }
+        let manifest = SplitManifest { name, size, part };
+        let manifest_name = format!("{}{MANIFEST_SUFFIX}", manifest.name);
+        let contents = toml::to_string(&manifest)
+            .map_err(|e| Error::SplitError(format!("cannot serialize the manifest: {e}")))?;
+        let uploaded = self
+            .uploader
+            .upload_stream_as("manifest", &manifest_name, contents.as_bytes())
+            .1?;
+        if self.uploader.config().dry_run {
+            // the URLs of the parts are the descriptions of the requests in dry runs
+            let mut requests = manifest
+                .part
+                .into_iter()
+                .map(|part| part.url)
+                .collect::<Vec<_>>();
+            requests.push(uploaded.url);
+            return Ok(Uploaded::from(requests.join("\n\n")));
+        }
+        Ok(uploaded)
+    }
+
+    /// Deletes the uploaded parts of a file that cannot be uploaded completely.
+    ///
+    /// The parts that cannot be deleted are reported.
+    fn delete_parts(&self, parts: &[Part]) {
+        if self.uploader.config().dry_run {
+            return;
+        }
+        for part in parts {
+            let result = Url::parse(&part.url)
+                .map_err(Error::from)
+                .and_then(|url| self.uploader.config().server.file_name(&url))
+                .and_then(|file_name| match file_name {
+                    Some(file_name) => self.uploader.delete(&file_name),
+                    None => Err(Error::DeleteError(String::from(
+                        "the URL does not belong to the server",
+                    ))),
+                });
+            match result {
+                Ok(_) => log::info!("Deleted the uploaded part {}", part.url),
+                Err(e) => eprintln!("Warning: cannot delete the uploaded part {}: {e}", part.url),
+            }
+        }
+    }
+
+    /// Uploads the part with the given index of the file.
+    fn upload_part(&self, file: &str, name: &str, index: usize, count: usize) -> Result<Part> {
+        let mut reader = File::open(file)?;
+        reader.seek(SeekFrom::Start(index as u64 * self.part_size))?;
+        let mut reader = HashingReader::new(reader.take(self.part_size));
+        let width = count.to_string().len().max(3);
+        let part_name = format!("{name}.{:0width$}", index + 1);
+        log::info!("Uploading part {} of {count} as {part_name}", index + 1);
+        let uploaded = self
+            .uploader
+            .upload_stream_as("part", &part_name, &mut reader)
+            .1
+            .map_err(|e| Error::SplitError(format!("cannot upload {part_name}: {e}")))?;
+        Ok(Part {
+            url: uploaded.url,
+            size: self.part_size - reader.get_ref().limit(),
+            sha256: reader.finish(),
+        })
+    }
+}
+
+/// Downloads the file at the given URL into the writer, authenticated if the URL belongs to
+/// the server or one of the profiles.
+fn download<W: Write>(config: &mut Config, url: &str, writer: &mut W) -> Result<u64> {
+    let config = match config.locate_paste(url) {
+        Ok(Some((config, _))) => config,
+        _ => config.clone(),
+    };
+    Uploader::new(&config).download(url, writer)
+}
+
+/// Downloads the split manifest at the given URL and the parts that it lists, then joins the
+/// parts into the file in the given directory.
+///
+/// The sizes and the hashes of the parts are verified, the file is removed if they do not
+/// match. Returns the path of the file, or the description of the manifest request in dry
+/// runs.
+pub fn join(config: &Config, url: &str, dir: &Path) -> Result<String> {
+    // the tokens of the profiles are only read once
+    let config = &mut config.clone();
+    let mut contents = Vec::new();
+    download(config, url, &mut contents)?;
+    if config.dry_run {
+        return Ok(String::from_utf8_lossy(&contents).to_string());
+    }
+    let manifest: SplitManifest = toml::from_str(&String::from_utf8_lossy(&contents))?;
+    // the name is not trusted to contain a path
+    let name = Path::new(&manifest.name)
+        .file_name()
+        .ok_or_else(|| Error::SplitError(format!("invalid file name: {:?}", manifest.name)))?;
+    let path = dir.join(name);
+    let mut file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| Error::SplitError(format!("cannot create {}: {e}", path.display())))?;
+    let result = join_parts(config, &manifest, &mut file);
+    if result.is_err() {
+        fs::remove_file(&path)?;
+    }
+    result.map(|_| path.display().to_string())
+}
+
+/// Downloads the parts of the manifest into the writer while verifying them.
+fn join_parts<W: Write>(
+    config: &mut Config,
+    manifest: &SplitManifest,
+    writer: &mut W,
+) -> Result<()> {
+    let mut size = 0;
+    for (index, part) in manifest.part.iter().enumerate() {
+        log::info!("Downloading part {} of {}", index + 1, manifest.part.len());
+        let mut hasher = Hasher::default();
+        let part_size = download(config, &part.url, &mut TeeWriter(writer, &mut hasher))?;
+        let sha256 = hasher.finish();
+        if part_size != part.size || sha256 != part.sha256 {
+            return Err(Error::VerifyError(format!(
+                "{} does not match the manifest (size: {part_size}, sha256: {sha256}, expected size: {}, expected sha256: {})",
+                part.url, part.size, part.sha256
+            )));
+        }
+        size += part_size;
+    }
+    if size != manifest.size {
+        return Err(Error::VerifyError(format!(
+            "the parts are {size} bytes in total, expected {} bytes",
+            manifest.size
+        )));
+    }
+    Ok(())
+}
+
+/// Writer that writes the data to both of the writers.
+struct TeeWriter<'w, A: Write, B: Write>(&'w mut A, &'w mut B);
+
+impl<A: Write, B: Write> Write for TeeWriter<'_, A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes_written = self.0.write(buf)?;
+        self.1.write_all(&buf[..bytes_written])?;
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()?;
+        self.1.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum;
+
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(Some(1048576), parse_size("1048576"));
+        assert_eq!(Some(512_000), parse_size("512K"));
+        assert_eq!(Some(100_000_000), parse_size("100MB"));
+        assert_eq!(Some(1 << 30), parse_size("1GiB"));
+        assert_eq!(None, parse_size("0"));
+        assert_eq!(None, parse_size("10TB"));
+        assert_eq!(None, parse_size("MB"));
+    }
+
+    #[test]
+    fn serialize_manifest() -> Result<()> {
+        let manifest = SplitManifest {
+            name: String::from("trace.bin"),
+            size: 7,
+            part: vec![Part {
+                url: String::from("https://paste.example.com/trace.bin.001"),
+                size: 7,
+                sha256: checksum::hash("content".as_bytes())?,
+            }],
+        };
+        let contents = toml::to_string(&manifest).map_err(|e| Error::SplitError(e.to_string()))?;
+        assert_eq!(manifest, toml::from_str(&contents)?);
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_join_describes_manifest_request() -> Result<()> {
+        let mut config = Config::default();
+        config.server.address = String::from("http://127.0.0.1:9");
+        config.dry_run = true;
+        let dir = std::env::temp_dir().join(format!("rpaste-join-test-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let result = join(&config, "http://127.0.0.1:9/x.parts.toml", &dir);
+        let entries = fs::read_dir(&dir)?.count();
+        fs::remove_dir_all(&dir)?;
+        assert_eq!(
+            Some("GET http://127.0.0.1:9/x.parts.toml"),
+            result?.lines().next()
+        );
+        assert_eq!(0, entries);
+        Ok(())
+    }
+}
@@ -0,0 +1,580 @@
+use crate::datetime;
+use crate::error::Result;
+use crate::list::{self, ItemFilter, ListOptions, SortKey};
+use crate::upload::{ListItem, Uploader};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Interval for refreshing the file list.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Timeout for polling the terminal events.
+const POLL_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Maximum number of bytes to show in the preview.
+const PREVIEW_LIMIT: u64 = 64 * 1024;
+
+/// Number of rows to move with page up/down.
+const PAGE_SIZE: usize = 10;
+
+/// Help text shown in the footer.
+const HELP: &str = "q quit | / search | s sort | r reverse | space select | a select all | \
+d delete | enter preview | c copy URL | R refresh";
+
+/// Action to run after handling a key.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Nothing to do.
+    None,
+    /// Exit the application.
+    Quit,
+    /// Retrieve the file list again.
+    Refresh,
+    /// Show the contents of the given file.
+    Preview(String),
+    /// Delete the given files.
+    Delete(Vec<String>),
+    /// Copy the URL of the given file to the clipboard.
+    Copy(String),
+}
+
+/// Contents of a file shown next to the list.
+#[derive(Debug)]
+struct Preview {
+    /// Name of the file.
+    name: String,
+    /// Text to show.
+    text: String,
+}
+
+/// State of the terminal UI.
+#[derive(Debug)]
+struct App {
+    /// Files on the server.
+    items: Vec<ListItem>,
+    /// Files that are shown, sorted and matching the search query.
+    visible: Vec<ListItem>,
+    /// Key to sort the files by.
+    sort: SortKey,
+    /// Whether if the order is reversed.
+    reverse: bool,
+    /// Search query for the file names.
+    query: String,
+    /// Whether if the search query is being typed.
+    searching: bool,
+    /// Names of the selected files.
+    selected: HashSet<String>,
+    /// State of the table (i.e. the highlighted row).
+    table: TableState,
+    /// Preview of the highlighted file.
+    preview: Option<Preview>,
+    /// Files waiting for confirmation to be deleted.
+    pending_delete: Option<Vec<String>>,
+    /// Status message shown in the footer.
+    status: Option<String>,
+    /// Time of the last refresh.
+    last_refresh: Instant,
+}
+
+impl App {
+    /// Constructs a new instance with the given items.
+    fn new(items: Vec<ListItem>) -> Self {
+        let mut app = Self {
+            items: Vec::new(),
+            visible: Vec::new(),
+            sort: SortKey::Name,
+            reverse: false,
+            query: String::new(),
+            searching: false,
+            selected: HashSet::new(),
+            table: TableState::default(),
+            preview: None,
+            pending_delete: None,
+            status: None,
+            last_refresh: Instant::now(),
+        };
+        app.set_items(items);
+        app
+    }
+
+    /// Replaces the items, keeping the highlighted and the selected files if they still exist.
+    fn set_items(&mut self, items: Vec<ListItem>) {
+        self.selected
+            .retain(|name| items.iter().any(|item| &item.file_name == name));
+        self.items = items;
+        self.last_refresh = Instant::now();
+        self.update_visible();
+    }
+
+    /// Sorts and filters the items to show, keeping the highlighted file if it is still shown.
+    fn update_visible(&mut self) {
+        let current = self.current().map(|item| item.file_name.clone());
+        let query = self.query.to_lowercase();
+        let options = ListOptions {
+            sort: Some(self.sort),
+            reverse: self.reverse,
+            ..ListOptions::default()
+        };
+        self.visible = options.apply(self.items.clone());
+        self.visible
+            .retain(|item| item.file_name.to_lowercase().contains(&query));
+        let index = current
+            .and_then(|name| self.visible.iter().position(|item| item.file_name == name))
+            .or_else(|| self.table.selected())
+            .map(|index| index.min(self.visible.len().saturating_sub(1)));
+        self.table.select(if self.visible.is_empty() {
+            None
+        } else {
+            index.or(Some(0))
+        });
+    }
+
+    /// Returns the highlighted file.
+    fn current(&self) -> Option<&ListItem> {
+        self.table
+            .selected()
+            .and_then(|index| self.visible.get(index))
+    }
+
+    /// Moves the highlight by the given number of rows.
+    fn move_by(&mut self, rows: isize) {
+        if let Some(index) = self.table.selected() {
+            let last = self.visible.len().saturating_sub(1);
+            self.table
+                .select(Some(index.saturating_add_signed(rows).min(last)));
+        }
+    }
+
+    /// Handles the key press and returns the action to run.
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+        if let Some(files) = self.pending_delete.take() {
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                return Action::Delete(files);
+            }
+            self.status = Some(String::from("Deletion cancelled"));
+            return Action::None;
+        }
+        if self.searching {
+            match key.code {
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.searching = false;
+                }
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Char(c) => self.query.push(c),
+                _ => return Action::None,
+            }
+            self.update_visible();
+            return Action::None;
+        }
+        self.status = None;
+        match key.code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc if self.preview.is_some() => self.preview = None,
+            KeyCode::Esc if !self.query.is_empty() => {
+                self.query.clear();
+                self.update_visible();
+            }
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(PAGE_SIZE as isize),
+            KeyCode::PageUp => self.move_by(-(PAGE_SIZE as isize)),
+            KeyCode::Home | KeyCode::Char('g') => self.move_by(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX),
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('s') => {
+                self.sort = match self.sort {
+                    SortKey::Name => SortKey::Size,
+                    SortKey::Size => SortKey::Created,
+                    SortKey::Created => SortKey::Expiry,
+                    SortKey::Expiry => SortKey::Name,
+                };
+                self.update_visible();
+            }
+            KeyCode::Char('r') => {
+                self.reverse = !self.reverse;
+                self.update_visible();
+            }
+            KeyCode::Char(' ') => {
+                if let Some(name) = self.current().map(|item| item.file_name.clone()) {
+                    if !self.selected.remove(&name) {
+                        self.selected.insert(name);
+                    }
+                    self.move_by(1);
+                }
+            }
+            KeyCode::Char('a') => {
+                if self
+                    .visible
+                    .iter()
+                    .all(|item| self.selected.contains(&item.file_name))
+                {
+                    self.selected.clear();
+                } else {
+                    self.selected
+                        .extend(self.visible.iter().map(|item| item.file_name.clone()));
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                let mut files = self
+                    .items
+                    .iter()
+                    .map(|item| item.file_name.clone())
+                    .filter(|name| self.selected.contains(name))
+                    .collect::<Vec<_>>();
+                if files.is_empty() {
+                    files.extend(self.current().map(|item| item.file_name.clone()));
+                }
+                if !files.is_empty() {
+                    self.pending_delete = Some(files);
+                }
+            }
+            KeyCode::Enter | KeyCode::Char('p') => {
+                if let Some(item) = self.current() {
+                    return Action::Preview(item.file_name.clone());
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(item) = self.current() {
+                    return Action::Copy(item.file_name.clone());
+                }
+            }
+            KeyCode::Char('R') | KeyCode::F(5) => return Action::Refresh,
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Retrieves the file list again.
+    fn refresh(&mut self, uploader: &Uploader) {
+        match uploader.retrieve_items() {
+            Ok(items) => self.set_items(items),
+            Err(e) => {
+                self.last_refresh = Instant::now();
+                self.status = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Retrieves the contents of the given file for previewing.
+    fn preview(&mut self, uploader: &Uploader, name: String) {
+        // one shot URLs are both one shot items and URLs
+        let is_type = |item_type: &str| {
+            self.items
+                .iter()
+                .find(|item| item.file_name == name)
+                .is_some_and(|item| ItemFilter::Types(vec![item_type.to_string()]).matches(item))
+        };
+        let text = if is_type("oneshot") {
+            String::from("Previewing one shot files would expire them.")
+        } else if is_type("url") {
+            String::from("Shortened URLs cannot be previewed.")
+        } else {
+            match uploader.retrieve_file(&name, PREVIEW_LIMIT) {
+                Ok(contents) => preview_text(&contents),
+                Err(e) => e.to_string(),
+            }
+        };
+        self.preview = Some(Preview { name, text });
+    }
+
+    /// Deletes the given files and retrieves the file list again.
+    fn delete(&mut self, uploader: &Uploader, files: Vec<String>) {
+        let mut errors = Vec::new();
+        for file in &files {
+            match uploader.delete(file) {
+                Ok(_) => {
+                    self.selected.remove(file);
+                }
+                Err(e) => errors.push(format!("{file}: {e}")),
+            }
+        }
+        self.refresh(uploader);
+        self.status = Some(match errors.first() {
+            None => format!("Deleted {} files", files.len()),
+            Some(error) => format!(
+                "Deleted {} of {} files ({error})",
+                files.len() - errors.len(),
+                files.len()
+            ),
+        });
+    }
+
+    /// Copies the URL of the given file to the clipboard.
+    ///
+    /// The URL is sent to the terminal via the OSC 52 escape sequence.
+    fn copy(&mut self, uploader: &Uploader, name: String) {
+        let result = uploader.retrieve_url(&name).and_then(|url| {
+            let mut stdout = io::stdout();
+            write!(
+                stdout,
+                "\x1b]52;c;{}\x07",
+                BASE64_STANDARD.encode(url.as_str())
+            )?;
+            stdout.flush()?;
+            Ok(url)
+        });
+        self.status = Some(match result {
+            Ok(url) => format!("Copied {url}"),
+            Err(e) => e.to_string(),
+        });
+    }
+
+    /// Draws the application.
+    fn draw(&mut self, frame: &mut Frame, server: &str) {
+        let [header_area, body_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        self.draw_header(frame, header_area, server);
+        if let Some(preview) = &self.preview {
+            let [table_area, preview_area] =
+                Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                    .areas(body_area);
+            frame.render_widget(
+                Paragraph::new(preview.text.as_str())
+                    .wrap(Wrap { trim: false })
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(format!(" {} ", preview.name)),
+                    ),
+                preview_area,
+            );
+            self.draw_table(frame, table_area);
+        } else {
+            self.draw_table(frame, body_area);
+        }
+        self.draw_footer(frame, footer_area);
+    }
+
+    /// Draws the server address, the sort order and the search query.
+    fn draw_header(&self, frame: &mut Frame, area: Rect, server: &str) {
+        let sort = match self.sort {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Created => "created",
+            SortKey::Expiry => "expiry",
+        };
+        let mut header = format!(
+            " rpaste | {server} | sort: {sort} {}",
+            if self.reverse { "↓" } else { "↑" }
+        );
+        if self.searching || !self.query.is_empty() {
+            header.push_str(&format!(" | search: {}", self.query));
+            if self.searching {
+                header.push('_');
+            }
+        }
+        frame.render_widget(Line::from(header).bold(), area);
+    }
+
+    /// Draws the file list.
+    fn draw_table(&mut self, frame: &mut Frame, area: Rect) {
+        let now = datetime::now();
+        let rows = self.visible.iter().map(|item| {
+            let mark = if self.selected.contains(&item.file_name) {
+                "●"
+            } else {
+                " "
+            };
+            let [name, size, item_type, created, expires] = list::table_row(item, now);
+            Row::new([mark.to_string(), name, size, item_type, created, expires])
+        });
+        let header = Row::new([""].into_iter().chain(list::HEADERS))
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(1),
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            " {} of {} files, {} selected ",
+            self.visible.len(),
+            self.items.len(),
+            self.selected.len()
+        )));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    /// Draws the confirmation prompt, the status message or the help text.
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let footer = if let Some(files) = &self.pending_delete {
+            Line::from(format!(" Delete {} files? (y/N)", files.len())).fg(Color::Red)
+        } else if let Some(status) = &self.status {
+            Line::from(format!(" {status}")).fg(Color::Yellow)
+        } else {
+            Line::from(format!(" {HELP}")).fg(Color::DarkGray)
+        };
+        frame.render_widget(footer, area);
+    }
+
+    /// Runs the event loop until the user quits.
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        uploader: &Uploader,
+        server: &str,
+    ) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame, server))?;
+            if event::poll(POLL_TIMEOUT)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    match self.handle_key(key) {
+                        Action::None => {}
+                        Action::Quit => return Ok(()),
+                        Action::Refresh => self.refresh(uploader),
+                        Action::Preview(name) => self.preview(uploader, name),
+                        Action::Delete(files) => self.delete(uploader, files),
+                        Action::Copy(name) => self.copy(uploader, name),
+                    }
+                }
+            }
+            if self.last_refresh.elapsed() >= REFRESH_INTERVAL && self.pending_delete.is_none() {
+                self.refresh(uploader);
+            }
+        }
+    }
+}
+
+/// Returns the text to preview for the given file contents.
+///
+/// Binary files are not shown, a multi-byte character cut by the size limit is ignored.
+fn preview_text(contents: &[u8]) -> String {
+    let text = match std::str::from_utf8(contents) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&contents[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return String::from("Binary file, no preview available."),
+    };
+    if text.contains('\0') {
+        return String::from("Binary file, no preview available.");
+    }
+    text.to_string()
+}
+
+/// Runs the interactive terminal UI for browsing and managing the files on server, starting
+/// with the given items.
+///
+/// `server` is the address of the server to show in the header.
+pub fn run(uploader: &Uploader, items: Vec<ListItem>, server: &str) -> Result<()> {
+    let mut app = App::new(items);
+    let mut terminal = ratatui::try_init()?;
+    let result = app.run(&mut terminal, uploader, server);
+    ratatui::try_restore()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn item(name: &str, size: u64) -> ListItem {
+        ListItem {
+            file_name: name.to_string(),
+            file_size: Some(size),
+            item_type: String::from("file"),
+            creation_date_utc: None,
+            expires_at_utc: None,
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Action {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn visible(app: &App) -> Vec<&str> {
+        app.visible
+            .iter()
+            .map(|item| item.file_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn search_and_sort_items() {
+        let mut app = App::new(vec![item("b.log", 1), item("a.txt", 3), item("c.log", 2)]);
+        assert_eq!(vec!["a.txt", "b.log", "c.log"], visible(&app));
+
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(vec!["b.log", "c.log", "a.txt"], visible(&app));
+
+        for code in [KeyCode::Char('/'), KeyCode::Char('L'), KeyCode::Char('o')] {
+            press(&mut app, code);
+        }
+        assert_eq!(vec!["b.log", "c.log"], visible(&app));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(3, app.visible.len());
+    }
+
+    #[test]
+    fn delete_selected_items_after_confirmation() {
+        let mut app = App::new(vec![item("a", 1), item("b", 1), item("c", 1)]);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(Action::None, press(&mut app, KeyCode::Char('d')));
+        assert_eq!(
+            Action::Delete(vec![String::from("a"), String::from("c")]),
+            press(&mut app, KeyCode::Char('y'))
+        );
+
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(Action::None, press(&mut app, KeyCode::Char('n')));
+        assert!(app.pending_delete.is_none());
+    }
+
+    #[test]
+    fn skip_preview_of_oneshot_urls() {
+        let mut config = Config::default();
+        config.server.address = String::from("http://127.0.0.1:1");
+        let uploader = Uploader::new(&config);
+        let mut oneshot_url = item("a", 1);
+        oneshot_url.item_type = String::from("oneshot_url");
+        let mut app = App::new(vec![oneshot_url]);
+        app.preview(&uploader, String::from("a"));
+        assert_eq!(
+            Some("Previewing one shot files would expire them."),
+            app.preview.as_ref().map(|preview| preview.text.as_str())
+        );
+    }
+
+    #[test]
+    fn preview_text_files_only() {
+        assert_eq!("hello", preview_text(b"hello"));
+        assert_eq!("caf", preview_text(&"café".as_bytes()[..4]));
+        assert_eq!(
+            "Binary file, no preview available.",
+            preview_text(&[0xff, 0xfe, 0x00, 0x41])
+        );
+    }
+}
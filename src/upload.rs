@@ -1,16 +1,34 @@
+use crate::checksum::Hasher;
 use crate::config::Config;
+use crate::datetime;
 use crate::error::{Error, Result};
+use crate::list::{self, ListFormat, ListOptions};
+use crate::select::Selection;
 use indicatif::{ProgressBar, ProgressStyle};
 use multipart::client::lazy::Multipart;
-use secrecy::ExposeSecret;
-use serde::Deserialize;
-use std::io::{Read, Result as IoResult, Write};
-use std::time::Duration;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Result as IoResult, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use ureq::http::{Response, Uri};
 #[cfg(feature = "use-native-certs")]
 use ureq::tls::{Certificate, RootCerts, TlsConfig};
-use ureq::{Agent, SendBody};
+use ureq::{Agent, Body, RequestBuilder, ResponseExt, SendBody};
 use url::Url;
 
+/// Description of the TLS implementation and the trusted root certificates.
+#[cfg(feature = "use-native-certs")]
+const TLS_DESCRIPTION: &str = "rustls with the native root certificates";
+
+/// Description of the TLS implementation and the trusted root certificates.
+#[cfg(not(feature = "use-native-certs"))]
+const TLS_DESCRIPTION: &str = "rustls with the webpki root certificates";
+
 /// Default file name to use for multipart stream.
 const DEFAULT_FILE_NAME: Option<&str> = Some("file");
 
@@ -21,7 +39,7 @@ const EXPIRATION_HEADER: &str = "expire";
 const FILENAME_HEADER: &str = "filename";
 
 /// File entry item for list endpoint.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ListItem {
     /// Uploaded file name.
     pub file_name: String,
@@ -45,6 +63,81 @@ fn item_type_default() -> String {
 #[derive(Debug)]
 pub struct UploadResult<'a, T>(pub &'a str, pub Result<T>);
 
+/// Result of a successful upload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uploaded {
+    /// URL of the uploaded file, or the description of the request in dry runs.
+    pub url: String,
+    /// SHA-256 digest of the content if it is verified, see [`Config::verify`].
+    pub sha256: Option<String>,
+}
+
+impl From<String> for Uploaded {
+    fn from(url: String) -> Self {
+        Self { url, sha256: None }
+    }
+}
+
+/// SHA-256 digest of the content of a multipart body with a single field.
+///
+/// The part header and the closing boundary are skipped while the body is streamed.
+#[derive(Debug)]
+struct BodyDigest {
+    /// Bytes of the part header read so far, until the empty line that ends the header.
+    header: Vec<u8>,
+    /// Bytes that might belong to the closing boundary.
+    pending: Vec<u8>,
+    /// Length of the closing boundary.
+    trailer_len: usize,
+    /// Hasher of the content.
+    hasher: Hasher,
+}
+
+impl BodyDigest {
+    /// End of the part header.
+    const HEADER_END: &'static [u8] = b"\r\n\r\n";
+
+    /// Constructs a new instance for the body with the given boundary.
+    fn new(boundary: &str) -> Self {
+        Self {
+            header: Vec::new(),
+            pending: Vec::new(),
+            trailer_len: format!("\r\n--{boundary}--").len(),
+            hasher: Hasher::default(),
+        }
+    }
+
+    /// Updates the digest with the next bytes of the body.
+    fn update(&mut self, mut bytes: &[u8]) {
+        if !self.header.ends_with(Self::HEADER_END) {
+            let start = self.header.len().saturating_sub(Self::HEADER_END.len() - 1);
+            self.header.extend_from_slice(bytes);
+            match self.header[start..]
+                .windows(Self::HEADER_END.len())
+                .position(|window| window == Self::HEADER_END)
+            {
+                Some(index) => {
+                    let header_len = start + index + Self::HEADER_END.len();
+                    bytes = &bytes[bytes.len() - (self.header.len() - header_len)..];
+                    self.header.truncate(header_len);
+                }
+                None => return,
+            }
+        }
+        self.pending.extend_from_slice(bytes);
+        if self.pending.len() > self.trailer_len {
+            let content_len = self.pending.len() - self.trailer_len;
+            self.hasher.update(&self.pending[..content_len]);
+            self.pending.drain(..content_len);
+        }
+    }
+
+    /// Returns the hex encoded digest of the content.
+    fn finalize(self) -> String {
+        self.hasher.finish()
+    }
+}
+
 /// Upload progress tracker.
 #[derive(Debug)]
 pub struct UploadTracker<'a, R: Read> {
@@ -54,6 +147,8 @@ pub struct UploadTracker<'a, R: Read> {
     progress_bar: &'a ProgressBar,
     /// Uploaded size.
     uploaded: usize,
+    /// Digest of the uploaded content.
+    digest: Option<BodyDigest>,
 }
 
 impl<'a, R: Read> UploadTracker<'a, R> {
@@ -70,19 +165,246 @@ impl<'a, R: Read> UploadTracker<'a, R> {
             inner: reader,
             progress_bar,
             uploaded: 0,
+            digest: None,
         })
     }
+
+    /// Computes the SHA-256 digest of the content while uploading a multipart body with a
+    /// single field and the given boundary.
+    pub fn with_digest(mut self, boundary: &str) -> Self {
+        self.digest = Some(BodyDigest::new(boundary));
+        self
+    }
+
+    /// Returns the hex encoded SHA-256 digest of the uploaded content, if it is computed.
+    pub fn digest(self) -> Option<String> {
+        self.digest.map(BodyDigest::finalize)
+    }
 }
 
 impl<R: Read> Read for UploadTracker<'_, R> {
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
         let bytes_read = self.inner.read(buf)?;
+        if let Some(digest) = self.digest.as_mut() {
+            digest.update(&buf[..bytes_read]);
+        }
         self.uploaded += bytes_read;
         self.progress_bar.set_position(self.uploaded as u64);
         Ok(bytes_read)
     }
 }
 
+/// Reader that counts the bytes read into a shared counter.
+#[derive(Debug)]
+struct CountingReader<R: Read> {
+    /// Inner reader.
+    inner: R,
+    /// Number of bytes read.
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + bytes_read as u64);
+        Ok(bytes_read)
+    }
+}
+
+/// Field of a multipart form.
+#[derive(Debug)]
+struct FormField {
+    /// Name of the field.
+    name: &'static str,
+    /// File name of the field.
+    filename: Option<String>,
+    /// Size of the field data in bytes, counted while reading streams.
+    size: Rc<Cell<u64>>,
+}
+
+impl fmt::Display for FormField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "field \"{}\"", self.name)?;
+        if let Some(filename) = &self.filename {
+            write!(f, ", filename \"{filename}\"")?;
+        }
+        write!(f, ", {} bytes", self.size.get())
+    }
+}
+
+/// Multipart form that keeps track of its fields for describing dry runs.
+struct Form<'d> {
+    /// Multipart data.
+    multipart: Multipart<'static, 'd>,
+    /// Fields of the form.
+    fields: Vec<FormField>,
+}
+
+impl<'d> Form<'d> {
+    /// Constructs a new instance.
+    fn new() -> Self {
+        Self {
+            multipart: Multipart::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds the file at the given path.
+    fn add_file(&mut self, name: &'static str, path: &'d str) {
+        self.multipart.add_file(name, path);
+        self.fields.push(FormField {
+            name,
+            filename: Path::new(path)
+                .file_name()
+                .map(|filename| filename.to_string_lossy().to_string()),
+            size: Rc::new(Cell::new(
+                fs::metadata(path)
+                    .map(|metadata| metadata.len())
+                    .unwrap_or_default(),
+            )),
+        });
+    }
+
+    /// Adds a stream with an optional file name.
+    fn add_stream<R: Read + 'd>(
+        &mut self,
+        name: &'static str,
+        stream: R,
+        filename: Option<&'static str>,
+    ) {
+        let size = Rc::new(Cell::new(0));
+        let stream = CountingReader {
+            inner: stream,
+            count: Rc::clone(&size),
+        };
+        self.multipart
+            .add_stream::<_, _, &str>(name, stream, filename, None);
+        self.fields.push(FormField {
+            name,
+            filename: filename.map(String::from),
+            size,
+        });
+    }
+}
+
+/// Value of an HTTP header.
+#[derive(Debug)]
+enum HeaderValue<'s> {
+    /// Plain text value.
+    Text(String),
+    /// Secret value that is only exposed when sending the request.
+    Secret(&'s SecretString),
+}
+
+impl fmt::Display for HeaderValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(value) => write!(f, "{value}"),
+            Self::Secret(_) => write!(f, "[REDACTED]"),
+        }
+    }
+}
+
+/// Method, URL and headers of an HTTP request.
+///
+/// The same instance is used for sending the request and for printing it in dry runs.
+#[derive(Debug)]
+struct RequestInfo<'s> {
+    /// HTTP method.
+    method: &'static str,
+    /// Request URL.
+    url: String,
+    /// HTTP headers.
+    headers: Vec<(&'static str, HeaderValue<'s>)>,
+}
+
+impl<'s> RequestInfo<'s> {
+    /// Constructs a new instance with the given token as the `Authorization` header.
+    fn new(method: &'static str, url: String, token: Option<&'s SecretString>) -> Self {
+        let mut headers = Vec::new();
+        if let Some(token) = token {
+            headers.push(("Authorization", HeaderValue::Secret(token)));
+        }
+        Self {
+            method,
+            url,
+            headers,
+        }
+    }
+
+    /// Adds a plain text header.
+    fn header(&mut self, name: &'static str, value: impl Into<String>) {
+        self.headers.push((name, HeaderValue::Text(value.into())));
+    }
+
+    /// Adds the headers to the given request.
+    fn apply<B>(&self, mut request: RequestBuilder<B>) -> RequestBuilder<B> {
+        for (name, value) in &self.headers {
+            request = match value {
+                HeaderValue::Text(value) => request.header(*name, value),
+                HeaderValue::Secret(value) => request.header(*name, value.expose_secret()),
+            };
+        }
+        request
+    }
+
+    /// Sends the request with the given function and logs the request, the response and the
+    /// elapsed time.
+    fn send(
+        &self,
+        send: impl FnOnce() -> std::result::Result<Response<Body>, ureq::Error>,
+    ) -> std::result::Result<Response<Body>, ureq::Error> {
+        log::info!("{} {}", self.method, self.url);
+        for (name, value) in &self.headers {
+            log::debug!("> {name}: {value}");
+        }
+        let start = Instant::now();
+        let result = send();
+        match &result {
+            Ok(response) => {
+                log::info!(
+                    "{:?} {} ({:.2?})",
+                    response.version(),
+                    response.status(),
+                    start.elapsed()
+                );
+                if let Some(history) = response.get_redirect_history() {
+                    for uri in history.iter().skip(1) {
+                        log::debug!("Redirected to {uri}");
+                    }
+                }
+                let uri = response.get_uri();
+                // the URL is compared after parsing since e.g. a trailing slash is added
+                if self.url.parse::<Uri>().ok().as_ref() != Some(uri) {
+                    log::info!("Final URL: {uri}");
+                }
+                if uri.scheme_str() == Some("https") {
+                    log::debug!("TLS: {TLS_DESCRIPTION}");
+                }
+                for (name, value) in response.headers() {
+                    if name == "set-cookie" {
+                        log::debug!("< {name}: [REDACTED]");
+                    } else {
+                        log::debug!("< {name}: {}", value.to_str().unwrap_or("<binary>"));
+                    }
+                }
+            }
+            Err(e) => log::info!("Request failed ({:.2?}): {e}", start.elapsed()),
+        }
+        result
+    }
+}
+
+impl fmt::Display for RequestInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)?;
+        for (name, value) in &self.headers {
+            write!(f, "\n{name}: {value}")?;
+        }
+        Ok(())
+    }
+}
+
 /// Upload handler.
 #[derive(Debug)]
 pub struct Uploader<'a> {
@@ -95,11 +417,14 @@ pub struct Uploader<'a> {
 impl<'a> Uploader<'a> {
     /// Constructs a new instance.
     pub fn new(config: &'a Config) -> Self {
-        let client_config = Agent::config_builder().user_agent(format!(
-            "{}/{}",
-            env!("CARGO_PKG_NAME"),
-            env!("CARGO_PKG_VERSION")
-        ));
+        let client_config = Agent::config_builder()
+            .user_agent(format!(
+                "{}/{}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ))
+            // the redirects are logged
+            .save_redirect_history(log::log_enabled!(log::Level::Debug));
         #[cfg(feature = "use-native-certs")]
         let client_config =
             client_config.tls_config(TlsConfig::builder().root_certs(native_root_certs()).build());
@@ -109,21 +434,54 @@ impl<'a> Uploader<'a> {
         }
     }
 
+    /// Returns the configuration of the server.
+    pub fn config(&self) -> &'a Config {
+        self.config
+    }
+
     /// Uploads the given file to the server.
-    pub fn upload_file(&self, file: &'a str) -> UploadResult<'a, String> {
+    pub fn upload_file(&self, file: &'a str) -> UploadResult<'a, Uploaded> {
+        self.upload_file_as(file, file, None)
+    }
+
+    /// Uploads the given file to the server with an optional file name.
+    ///
+    /// The file name takes precedence over the configured one.
+    pub fn upload_file_as(
+        &self,
+        label: &'a str,
+        file: &str,
+        filename: Option<&str>,
+    ) -> UploadResult<'a, Uploaded> {
         let field = if self.config.paste.oneshot == Some(true) {
             "oneshot"
         } else {
             "file"
         };
-        let mut multipart = Multipart::new();
-        multipart.add_file(field, file);
+        let mut form = Form::new();
+        form.add_file(field, file);
+
+        UploadResult(label, self.upload(form, filename))
+    }
 
-        UploadResult(file, self.upload(multipart))
+    /// Uploads the selected lines of the given file to the server with the given file name.
+    ///
+    /// The lines are read lazily while uploading.
+    pub fn upload_file_lines(
+        &self,
+        label: &'a str,
+        file: &str,
+        filename: &str,
+        selection: Selection,
+    ) -> UploadResult<'a, Uploaded> {
+        match File::open(file) {
+            Ok(f) => self.upload_stream_as(label, filename, selection.apply(BufReader::new(f))),
+            Err(e) => UploadResult(label, Err(e.into())),
+        }
     }
 
     /// Uploads the given URL (stream) to the server.
-    pub fn upload_url(&self, url: &'a str) -> UploadResult<'a, String> {
+    pub fn upload_url(&self, url: &'a str) -> UploadResult<'a, Uploaded> {
         let field = if self.config.paste.oneshot == Some(true) {
             "oneshot_url"
         } else {
@@ -133,64 +491,103 @@ impl<'a> Uploader<'a> {
         if let Err(e) = Url::parse(url) {
             UploadResult(url, Err(e.into()))
         } else {
-            let mut multipart = Multipart::new();
-            multipart.add_stream::<_, &[u8], &str>(field, url.as_bytes(), None, None);
-            UploadResult(url, self.upload(multipart))
+            let mut form = Form::new();
+            form.add_stream(field, url.as_bytes(), None);
+            UploadResult(url, self.upload(form, None))
         }
     }
 
     /// Uploads the given remote URL (stream) to the server.
-    pub fn upload_remote_url(&self, url: &'a str) -> UploadResult<'a, String> {
+    pub fn upload_remote_url(&self, url: &'a str) -> UploadResult<'a, Uploaded> {
         if let Err(e) = Url::parse(url) {
             UploadResult(url, Err(e.into()))
         } else {
-            let mut multipart = Multipart::new();
-            multipart.add_stream::<_, &[u8], &str>("remote", url.as_bytes(), None, None);
-            UploadResult(url, self.upload(multipart))
+            let mut form = Form::new();
+            form.add_stream("remote", url.as_bytes(), None);
+            UploadResult(url, self.upload(form, None))
         }
     }
 
     /// Uploads a stream to the server.
-    pub fn upload_stream<S: Read>(&self, stream: S) -> UploadResult<'a, String> {
+    pub fn upload_stream<S: Read>(&self, stream: S) -> UploadResult<'a, Uploaded> {
+        UploadResult("stream", self.upload_named_stream(stream, None))
+    }
+
+    /// Uploads a stream to the server with the given file name.
+    ///
+    /// The file name is sent via the filename header and takes precedence over the configured one.
+    pub fn upload_stream_as<S: Read>(
+        &self,
+        label: &'a str,
+        filename: &str,
+        stream: S,
+    ) -> UploadResult<'a, Uploaded> {
+        UploadResult(label, self.upload_named_stream(stream, Some(filename)))
+    }
+
+    /// Uploads a stream with an optional file name.
+    fn upload_named_stream<S: Read>(&self, stream: S, filename: Option<&str>) -> Result<Uploaded> {
         let field = if self.config.paste.oneshot == Some(true) {
             "oneshot"
         } else {
             "file"
         };
-        let mut multipart = Multipart::new();
-        multipart.add_stream(field, stream, DEFAULT_FILE_NAME, None);
-
-        UploadResult("stream", self.upload(multipart))
-    }
-
-    /// Uploads the given multipart data.
-    fn upload(&self, mut multipart: Multipart<'static, '_>) -> Result<String> {
-        let multipart_data = multipart.prepare()?;
-        let mut request = self
-            .client
-            .post(&self.config.server.address)
-            .config()
-            .http_status_as_error(false)
-            .build()
-            .header(
-                "Content-Type",
-                format!(
-                    "multipart/form-data; boundary={}",
-                    multipart_data.boundary()
-                ),
-            );
+        let mut form = Form::new();
+        form.add_stream(field, stream, DEFAULT_FILE_NAME);
+        self.upload(form, filename)
+    }
+
+    /// Uploads the given multipart form.
+    ///
+    /// `filename` overrides the configured [`PasteConfig::filename`](crate::config::PasteConfig::filename).
+    ///
+    /// If [`Config::verify`] is set, the uploaded file is fetched and compared with the content
+    /// that is sent, and its SHA-256 digest is returned along with the URL.
+    ///
+    /// Returns the description of the request without sending it in dry runs.
+    fn upload(&self, mut form: Form<'_>, filename: Option<&str>) -> Result<Uploaded> {
+        let mut multipart_data = form.multipart.prepare()?;
+        // the URLs are not stored as is and fetching the oneshot files deletes them
+        let verify =
+            self.config.verify && matches!(form.fields.as_slice(), [field] if field.name == "file");
+        let boundary = multipart_data.boundary().to_string();
+        let mut request_info = RequestInfo::new(
+            "POST",
+            self.config.server.address.to_string(),
+            self.config.server.auth_token.as_ref(),
+        );
+        request_info.header(
+            "Content-Type",
+            format!(
+                "multipart/form-data; boundary={}",
+                multipart_data.boundary()
+            ),
+        );
         if let Some(content_len) = multipart_data.content_len() {
-            request = request.header("Content-Length", content_len.to_string());
-        }
-        if let Some(auth_token) = &self.config.server.auth_token {
-            request = request.header("Authorization", auth_token.expose_secret());
+            request_info.header("Content-Length", content_len.to_string());
         }
         if let Some(expiration_time) = &self.config.paste.expire {
-            request = request.header(EXPIRATION_HEADER, expiration_time);
+            request_info.header(EXPIRATION_HEADER, expiration_time);
         }
-        if let Some(filename) = &self.config.paste.filename {
-            request = request.header(FILENAME_HEADER, filename);
+        if let Some(filename) = filename.or(self.config.paste.filename.as_deref()) {
+            request_info.header(FILENAME_HEADER, filename);
         }
+        if self.config.dry_run {
+            let body_size = io::copy(&mut multipart_data, &mut io::sink())?;
+            let mut description = request_info.to_string();
+            for field in &form.fields {
+                description.push_str(&format!("\n{field}"));
+            }
+            description.push_str(&format!("\nbody: {body_size} bytes"));
+            return Ok(Uploaded::from(description));
+        }
+        let request = request_info.apply(
+            self.client
+                .post(&self.config.server.address)
+                .config()
+                .http_status_as_error(false)
+                .build(),
+        );
         let progress_bar = ProgressBar::new_spinner();
         progress_bar.enable_steady_tick(Duration::from_millis(80));
         progress_bar.set_message("Uploading");
@@ -199,32 +596,110 @@ impl<'a> Uploader<'a> {
             multipart_data.content_len().unwrap_or_default(),
             multipart_data,
         )?;
-        let result = match request.send(SendBody::from_reader(&mut upload_tracker)) {
-            Ok(response) => {
-                let status = response.status();
-                let response_text = response.into_body().read_to_string()?;
-                if status.is_client_error() || status.is_server_error() {
-                    Err(Error::UploadError(format!(
-                        "{} (status code: {})",
-                        response_text.trim(),
-                        status.as_u16()
-                    )))
-                } else if response_text.lines().count() != 1 {
-                    Err(Error::UploadError(format!(
-                        "server returned invalid body (status code: {status})"
-                    )))
-                } else if status.as_u16() == 200 {
-                    Ok(response_text)
-                } else {
-                    Err(Error::UploadError(format!(
-                        "unknown error (status code: {status})"
-                    )))
+        if verify {
+            upload_tracker = upload_tracker.with_digest(&boundary);
+        }
+        let result =
+            match request_info.send(|| request.send(SendBody::from_reader(&mut upload_tracker))) {
+                Ok(response) => {
+                    let status = response.status();
+                    let response_text = response.into_body().read_to_string()?;
+                    if status.is_client_error() || status.is_server_error() {
+                        Err(Error::UploadError(format!(
+                            "{} (status code: {})",
+                            response_text.trim(),
+                            status.as_u16()
+                        )))
+                    } else if response_text.lines().count() != 1 {
+                        Err(Error::UploadError(format!(
+                            "server returned invalid body (status code: {status})"
+                        )))
+                    } else if status.as_u16() == 200 {
+                        Ok(response_text)
+                    } else {
+                        Err(Error::UploadError(format!(
+                            "unknown error (status code: {status})"
+                        )))
+                    }
                 }
-            }
-            Err(e) => Err(Error::RequestError(e)),
-        };
+                Err(e) => Err(Error::RequestError(e)),
+            };
         progress_bar.finish_and_clear();
-        result
+        let url = result?;
+        let sha256 = upload_tracker.digest();
+        if let Some(digest) = &sha256 {
+            self.verify(url.trim(), digest)?;
+        }
+        Ok(Uploaded {
+            url: self.config.server.to_public_url(url.trim()),
+            sha256,
+        })
+    }
+
+    /// Fetches the uploaded file at the given URL and compares its SHA-256 digest with the
+    /// digest of the content that is sent.
+    pub fn verify(&self, url: &str, digest: &str) -> Result<()> {
+        let mut hasher = Hasher::default();
+        self.download(url, &mut hasher)?;
+        let fetched = hasher.finish();
+        if fetched != digest {
+            return Err(Error::VerifyError(format!(
+                "{url} does not match the uploaded content (sha256: {fetched}, expected: {digest})"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Downloads the file at the given URL into the writer and returns its size.
+    ///
+    /// The file is downloaded from the server address with the auth token if the URL belongs to
+    /// the server, see [`ServerConfig::file_name`](crate::config::ServerConfig::file_name).
+    ///
+    /// Writes the description of the request instead of sending it in dry runs.
+    pub fn download<W: Write>(&self, url: &str, writer: &mut W) -> Result<u64> {
+        let request_info = self.file_request(url)?;
+        if self.config.dry_run {
+            let description = request_info.to_string();
+            writer.write_all(description.as_bytes())?;
+            return Ok(description.len() as u64);
+        }
+        let request = request_info.apply(self.client.get(&request_info.url));
+        let response = request_info.send(|| request.call())?;
+        Ok(io::copy(&mut response.into_body().as_reader(), writer)?)
+    }
+
+    /// Returns `true` if the file at the given URL can still be fetched.
+    ///
+    /// The content of the file is not read.
+    pub fn exists(&self, url: &str) -> Result<bool> {
+        let request_info = self.file_request(url)?;
+        let request = request_info.apply(
+            self.client
+                .get(&request_info.url)
+                .config()
+                .http_status_as_error(false)
+                .build(),
+        );
+        let status = request_info.send(|| request.call())?.status();
+        if status.is_server_error() {
+            return Err(Error::RequestError(ureq::Error::StatusCode(
+                status.as_u16(),
+            )));
+        }
+        Ok(status.is_success())
+    }
+
+    /// Returns the request for fetching the file at the given URL, with the auth token if the
+    /// URL belongs to the server.
+    fn file_request(&self, url: &str) -> Result<RequestInfo<'_>> {
+        match self.config.server.file_name(&Url::parse(url)?) {
+            Ok(Some(file_name)) => Ok(RequestInfo::new(
+                "GET",
+                self.file_url(&file_name)?.to_string(),
+                self.config.server.auth_token.as_ref(),
+            )),
+            _ => Ok(RequestInfo::new("GET", url.to_string(), None)),
+        }
     }
 
     /// Wrapper: Delete the given file from the server.
@@ -233,18 +708,26 @@ impl<'a> Uploader<'a> {
     }
 
     /// Delete the given file from the server.
-    fn delete(&self, file: &'a str) -> Result<String> {
-        let url = self.retrieve_url(file)?;
-        let mut request = self
-            .client
-            .delete(url.as_str())
-            .config()
-            .http_status_as_error(false)
-            .build();
-        if let Some(delete_token) = &self.config.server.delete_token {
-            request = request.header("Authorization", delete_token.expose_secret());
-        }
-        let result = match request.call() {
+    ///
+    /// Returns the description of the request without sending it in dry runs.
+    pub fn delete(&self, file: &str) -> Result<String> {
+        let url = self.file_url(file)?;
+        let request_info = RequestInfo::new(
+            "DELETE",
+            url.to_string(),
+            self.config.server.delete_token.as_ref(),
+        );
+        if self.config.dry_run {
+            return Ok(request_info.to_string());
+        }
+        let request = request_info.apply(
+            self.client
+                .delete(url.as_str())
+                .config()
+                .http_status_as_error(false)
+                .build(),
+        );
+        let result = match request_info.send(|| request.call()) {
             Ok(response) => {
                 let status = response.status();
                 let response_text = response.into_body().read_to_string()?;
@@ -269,8 +752,17 @@ impl<'a> Uploader<'a> {
         result
     }
 
-    /// Returns a valid request URL for an endpoint.
+    /// Returns the URL of an endpoint to show to the users.
+    ///
+    /// The URL starts with the [public URL](crate::config::ServerConfig::public_url) if it is
+    /// configured.
     pub fn retrieve_url(&self, endpoint: &str) -> Result<Url> {
+        let url = self.request_url(endpoint)?;
+        Ok(Url::parse(&self.config.server.to_public_url(url.as_str()))?)
+    }
+
+    /// Returns a valid request URL for an endpoint.
+    fn request_url(&self, endpoint: &str) -> Result<Url> {
         let mut url = Url::parse(&self.config.server.address)?;
         if !url.path().to_string().ends_with('/') {
             url = url.join(&format!("{}/", url.path()))?;
@@ -279,84 +771,116 @@ impl<'a> Uploader<'a> {
         Ok(url)
     }
 
+    /// Returns the URL of the file with the given name on the server.
+    ///
+    /// Unlike [`Uploader::request_url`], the name is encoded as a single path segment, so that
+    /// the names containing e.g. `?` or `#` refer to the file itself.
+    fn file_url(&self, file_name: &str) -> Result<Url> {
+        let mut url = Url::parse(&self.config.server.address)?;
+        url.path_segments_mut()
+            .map_err(|_| url::ParseError::RelativeUrlWithCannotBeABaseBase)?
+            .pop_if_empty()
+            .push(file_name);
+        Ok(url)
+    }
+
+    /// Returns the request for the given endpoint, authenticated with the auth token.
+    fn get_request(&self, endpoint: &str) -> Result<RequestInfo<'_>> {
+        Ok(RequestInfo::new(
+            "GET",
+            self.request_url(endpoint)?.to_string(),
+            self.config.server.auth_token.as_ref(),
+        ))
+    }
+
     /// Returns the server version.
+    ///
+    /// Returns the description of the request without sending it in dry runs.
     pub fn retrieve_version(&self) -> Result<String> {
-        let url = self.retrieve_url("version")?;
-        let mut request = self.client.get(url.as_str());
-        if let Some(auth_token) = &self.config.server.auth_token {
-            request = request.header("Authorization", auth_token.expose_secret());
+        let request_info = self.get_request("version")?;
+        if self.config.dry_run {
+            return Ok(request_info.to_string());
         }
-        Ok(request.call()?.body_mut().read_to_string()?)
+        let request = request_info.apply(self.client.get(&request_info.url));
+        Ok(request_info
+            .send(|| request.call())?
+            .body_mut()
+            .read_to_string()?)
+    }
+
+    /// Checks whether if the server is available, waiting at most `timeout` for the response.
+    ///
+    /// The server is considered to be unavailable if it cannot be connected to, it does not
+    /// respond in time or it responds with a server error (5xx).
+    pub fn check_health(&self, timeout: Duration) -> Result<()> {
+        let request_info = self.get_request("version")?;
+        let request = request_info.apply(
+            self.client
+                .get(&request_info.url)
+                .config()
+                .http_status_as_error(false)
+                .timeout_global(Some(timeout))
+                .build(),
+        );
+        let status = request_info.send(|| request.call())?.status();
+        if status.is_server_error() {
+            return Err(Error::RequestError(ureq::Error::StatusCode(
+                status.as_u16(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Retrieves the contents of the given file on server, reading at most `limit` bytes.
+    pub fn retrieve_file(&self, file: &str, limit: u64) -> Result<Vec<u8>> {
+        let request_info = self.get_request(file)?;
+        let request = request_info.apply(self.client.get(&request_info.url));
+        let mut contents = Vec::new();
+        request_info
+            .send(|| request.call())?
+            .into_body()
+            .into_reader()
+            .take(limit)
+            .read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Sends a request to the list endpoint.
+    fn list_response(&self) -> Result<Response<Body>> {
+        let request_info = self.get_request("list")?;
+        let request = request_info.apply(self.client.get(&request_info.url));
+        Ok(request_info.send(|| request.call())?)
+    }
+
+    /// Retrieves the files on server.
+    pub fn retrieve_items(&self) -> Result<Vec<ListItem>> {
+        Ok(self.list_response()?.body_mut().read_json()?)
     }
 
     /// Retrieves and prints the files on server.
-    pub fn retrieve_list<Output: Write>(&self, output: &mut Output, prettify: bool) -> Result<()> {
-        let url = self.retrieve_url("list")?;
-        let mut request = self.client.get(url.as_str());
-        if let Some(auth_token) = &self.config.server.auth_token {
-            request = request.header("Authorization", auth_token.expose_secret());
-        }
-        let mut response = request.call()?;
-        if !prettify {
-            writeln!(output, "{}", response.body_mut().read_to_string()?)?;
+    ///
+    /// The list is printed as returned by the server unless it is filtered, sorted, exported or
+    /// prettified. The description of the request is printed instead in dry runs.
+    pub fn retrieve_list<Output: Write>(
+        &self,
+        output: &mut Output,
+        options: &ListOptions,
+    ) -> Result<()> {
+        if self.config.dry_run {
+            writeln!(output, "{}", self.get_request("list")?)?;
             return Ok(());
         }
-        let items: Vec<ListItem> = response.body_mut().read_json()?;
-        if items.is_empty() {
-            writeln!(output, "No files on server :(")?;
+        let mut response = self.list_response()?;
+        if options.is_raw() {
+            writeln!(output, "{}", response.body_mut().read_to_string()?)?;
             return Ok(());
         }
-        let filename_width = items
-            .iter()
-            .map(|v| v.file_name.len())
-            .max()
-            .unwrap_or_default();
-        let mut filesize_width = items
-            .iter()
-            .map(|v| v.file_size.unwrap_or_default())
-            .max()
-            .unwrap_or_default()
-            .to_string()
-            .len();
-        if filesize_width < 4 {
-            filesize_width = 4;
-        }
-        let mut itemtype_width = items
-            .iter()
-            .map(|v| v.item_type.len())
-            .max()
-            .unwrap_or_default();
-        if itemtype_width < 4 {
-            itemtype_width = 4;
-        }
-        writeln!(
-            output,
-            "{:^filename_width$} | {:^filesize_width$} | {:^itemtype_width$} | {:^19} | {:^19}",
-            "Name", "Size", "Type", "Creation (UTC)", "Expiry (UTC)"
-        )?;
-        writeln!(
-            output,
-            "{:-<filename_width$}-|-{:->filesize_width$}-|-{:->itemtype_width$}-|-{:-<19}-|-{:-<19}",
-            "", "", "", "", ""
-        )?;
-        items.iter().try_for_each(|file_info| {
-            writeln!(
-                output,
-                "{:<filename_width$} | {:>filesize_width$} | {:<itemtype_width$} | {:<19} | {}",
-                file_info.file_name,
-                match file_info.file_size {
-                    Some(size) => size.to_string(),
-                    None => "n/a".to_string(),
-                },
-                file_info.item_type,
-                file_info
-                    .creation_date_utc
-                    .as_deref()
-                    .unwrap_or("info not available"),
-                file_info.expires_at_utc.as_deref().unwrap_or_default()
-            )
-        })?;
-        Ok(())
+        let items = options.apply(response.body_mut().read_json()?);
+        match (options.format, options.prettify) {
+            (Some(format), _) => list::write_items(output, &items, format),
+            (None, false) => list::write_items(output, &items, ListFormat::Json),
+            (None, true) => list::write_table(output, &items, datetime::now(), options.width),
+        }
     }
 }
 
@@ -373,6 +897,7 @@ fn native_root_certs() -> RootCerts {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::checksum;
     use std::io::{Read, Write};
     use std::net::TcpListener;
     use std::sync::mpsc::{self, Receiver};
@@ -394,7 +919,7 @@ mod tests {
                 .expect("test server should accept a request");
             let mut request = Vec::new();
             let mut buffer = [0; 1024];
-            let (header_len, content_len) = loop {
+            let (header_len, content_len, chunked) = loop {
                 let bytes_read = stream
                     .read(&mut buffer)
                     .expect("test server should read request headers");
@@ -410,10 +935,15 @@ mod tests {
                         })
                         .and_then(|value| value.parse().ok())
                         .unwrap_or_default();
-                    break (header_len, content_len);
+                    let chunked = headers
+                        .to_ascii_lowercase()
+                        .contains("transfer-encoding: chunked");
+                    break (header_len, content_len, chunked);
                 }
             };
-            while request.len() < header_len + content_len {
+            while request.len() < header_len + content_len
+                || (chunked && !request.ends_with(b"0\r\n\r\n"))
+            {
                 let bytes_read = stream
                     .read(&mut buffer)
                     .expect("test server should read the request body");
@@ -511,6 +1041,24 @@ mod tests {
         server.join().expect("test server should stop cleanly");
     }
 
+    #[test]
+    fn health_check_fails_on_server_errors_only() {
+        let (address, server) = test_server("404 Not Found", "not found\n");
+        let available = config(address);
+        let result = Uploader::new(&available).check_health(Duration::from_secs(5));
+        assert!(result.is_ok(), "{result:?}");
+        server.join().expect("test server should stop cleanly");
+
+        let (address, server) = test_server("503 Service Unavailable", "down\n");
+        let unavailable = config(address);
+        let result = Uploader::new(&unavailable).check_health(Duration::from_secs(5));
+        assert!(matches!(
+            result,
+            Err(Error::RequestError(ureq::Error::StatusCode(503)))
+        ));
+        server.join().expect("test server should stop cleanly");
+    }
+
     #[test]
     fn version_request_sends_auth_token() {
         let (address, request, server) = header_test_server("200 OK", "0.15.0\n");
@@ -544,4 +1092,66 @@ mod tests {
             .contains("authorization: delete-secret"));
         server.join().expect("test server should stop cleanly");
     }
+
+    #[test]
+    fn dry_run_describes_request_without_sending() {
+        let mut config = config(String::from("http://127.0.0.1:1"));
+        config.server.auth_token = Some("auth-secret".into());
+        config.paste.expire = Some(String::from("1h"));
+        config.dry_run = true;
+
+        let result = Uploader::new(&config)
+            .upload_stream_as("stream", "a.txt", "content".as_bytes())
+            .1
+            .expect("dry run should not send the request")
+            .url;
+
+        let lines = result.lines().collect::<Vec<_>>();
+        assert_eq!("POST http://127.0.0.1:1", lines[0]);
+        assert_eq!("Authorization: [REDACTED]", lines[1]);
+        assert!(lines[2].starts_with("Content-Type: multipart/form-data; boundary="));
+        assert_eq!("expire: 1h", lines[3]);
+        assert_eq!("filename: a.txt", lines[4]);
+        assert_eq!(r#"field "file", filename "file", 7 bytes"#, lines[5]);
+        assert!(lines[6].starts_with("body: "));
+        assert!(!result.contains("auth-secret"));
+    }
+
+    #[test]
+    fn delete_url_keeps_special_characters_in_name() -> Result<()> {
+        let mut config = config(String::from("http://127.0.0.1:1/paste"));
+        config.dry_run = true;
+        let url = Url::parse("http://127.0.0.1:1/paste/a%3Fb%23c.txt")?;
+        let file_name = config
+            .server
+            .file_name(&url)?
+            .expect("URL should belong to the server");
+        assert_eq!("a?b#c.txt", file_name);
+
+        let result = Uploader::new(&config).delete(&file_name)?;
+        assert_eq!(
+            Some("DELETE http://127.0.0.1:1/paste/a%3Fb%23c.txt"),
+            result.lines().next()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn digest_skips_multipart_framing() -> Result<()> {
+        let content = "content\r\n\r\nof the file\r\n--".repeat(10);
+        let mut form = Form::new();
+        form.add_stream("file", content.as_bytes(), DEFAULT_FILE_NAME);
+        let multipart_data = form.multipart.prepare()?;
+        let boundary = multipart_data.boundary().to_string();
+        let progress_bar = ProgressBar::hidden();
+        let mut upload_tracker =
+            UploadTracker::new(&progress_bar, 0, multipart_data)?.with_digest(&boundary);
+        let mut buffer = [0; 7];
+        while upload_tracker.read(&mut buffer)? != 0 {}
+        assert_eq!(
+            Some(checksum::hash(content.as_bytes())?),
+            upload_tracker.digest()
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,229 @@
+use crate::error::Result;
+use crate::upload::{UploadResult, Uploaded, Uploader};
+use glob::Pattern;
+use inotify::{EventMask, Inotify, WatchMask};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Default name of the manifest file that is created in the watched directory.
+pub const DEFAULT_MANIFEST_FILE: &str = ".rpaste-manifest";
+
+/// Duration that a file should stay unchanged before it is uploaded.
+const STABLE_DURATION: Duration = Duration::from_secs(1);
+
+/// Interval for polling the file system events.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Size of the buffer for reading file system events.
+const EVENT_BUFFER_SIZE: usize = 4096;
+
+/// Size and modification time of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    /// Size of the file in bytes.
+    size: u64,
+    /// Modification time of the file.
+    modified: Option<SystemTime>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the given file.
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        metadata.is_file().then(|| Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Directory watcher that uploads new or changed files.
+#[derive(Debug)]
+pub struct Watcher<'a> {
+    /// Upload handler.
+    uploader: &'a Uploader<'a>,
+    /// Directory to watch.
+    dir: PathBuf,
+    /// Patterns that the file names should match, all files are included if empty.
+    include: Vec<Pattern>,
+    /// File to write the upload records to.
+    manifest: PathBuf,
+}
+
+impl<'a> Watcher<'a> {
+    /// Constructs a new instance.
+    ///
+    /// The manifest is written to [`DEFAULT_MANIFEST_FILE`] in the watched directory if not given.
+    pub fn new(
+        uploader: &'a Uploader<'a>,
+        dir: PathBuf,
+        include: &[String],
+        manifest: Option<PathBuf>,
+    ) -> Result<Self> {
+        let include = include
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<std::result::Result<_, _>>()?;
+        let manifest = manifest.unwrap_or_else(|| dir.join(DEFAULT_MANIFEST_FILE));
+        Ok(Self {
+            uploader,
+            dir,
+            include,
+            manifest,
+        })
+    }
+
+    /// Returns the canonical path of the given file, which does not need to exist.
+    fn resolve(path: &Path) -> Option<PathBuf> {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        Some(parent.canonicalize().ok()?.join(path.file_name()?))
+    }
+
+    /// Returns `true` if the given file is the manifest.
+    fn is_manifest(&self, path: &Path) -> bool {
+        let manifest = Self::resolve(&self.manifest);
+        manifest.is_some() && Self::resolve(path) == manifest
+    }
+
+    /// Returns `true` if the file with the given name should be uploaded.
+    fn is_included(&self, name: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(name))
+    }
+
+    /// Watches the directory until interrupted.
+    ///
+    /// Files that are created, moved into or closed after writing are uploaded once their size
+    /// and modification time stay the same for a while. `on_upload` is called with each result.
+    pub fn run<F: FnMut(UploadResult<'_, Uploaded>)>(&self, mut on_upload: F) -> Result<()> {
+        let mut inotify = Inotify::init()?;
+        inotify.watches().add(
+            &self.dir,
+            WatchMask::CREATE | WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO,
+        )?;
+        let mut buffer = [0; EVENT_BUFFER_SIZE];
+        let mut pending: HashMap<PathBuf, (Option<Snapshot>, Instant)> = HashMap::new();
+        let mut uploaded: HashMap<PathBuf, Snapshot> = HashMap::new();
+        loop {
+            match inotify.read_events(&mut buffer) {
+                Ok(events) => {
+                    for event in events {
+                        let Some(name) = event.name else { continue };
+                        let path = self.dir.join(name);
+                        if event.mask.contains(EventMask::ISDIR)
+                            || !self.is_included(&name.to_string_lossy())
+                            || self.is_manifest(&path)
+                        {
+                            continue;
+                        }
+                        pending.insert(path, (None, Instant::now()));
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let now = Instant::now();
+            let mut stable = Vec::new();
+            pending.retain(|path, (last, since)| {
+                let Some(current) = Snapshot::of(path) else {
+                    return false;
+                };
+                if *last != Some(current) {
+                    *last = Some(current);
+                    *since = now;
+                    true
+                } else if now.duration_since(*since) >= STABLE_DURATION {
+                    stable.push((path.clone(), current));
+                    false
+                } else {
+                    true
+                }
+            });
+            for (path, snapshot) in stable {
+                if uploaded.get(&path) == Some(&snapshot) {
+                    continue;
+                }
+                let file = path.to_string_lossy().to_string();
+                let result = self.uploader.upload_file(&file);
+                if let Ok(upload) = &result.1 {
+                    self.record(&file, &upload.url)?;
+                    uploaded.insert(path, snapshot);
+                }
+                on_upload(result);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Appends an upload record to the manifest file.
+    ///
+    /// Records are tab separated values of the upload time (UNIX timestamp), file and URL.
+    fn record(&self, file: &str, url: &str) -> Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.manifest)?;
+        writeln!(manifest, "{timestamp}\t{file}\t{url}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn include_patterns_match_file_names() {
+        let config = Config::default();
+        let uploader = Uploader::new(&config);
+        let include = vec![String::from("*.log"), String::from("core.*")];
+        let watcher = Watcher::new(&uploader, PathBuf::from("artifacts"), &include, None)
+            .expect("watcher should be created");
+
+        assert!(watcher.is_included("build.log"));
+        assert!(watcher.is_included("core.1234"));
+        assert!(!watcher.is_included("build.txt"));
+        assert_eq!(
+            PathBuf::from("artifacts").join(DEFAULT_MANIFEST_FILE),
+            watcher.manifest
+        );
+    }
+
+    #[test]
+    fn default_manifest_is_excluded_before_it_exists() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rpaste-watch-test-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let config = Config::default();
+        let uploader = Uploader::new(&config);
+        let watcher = Watcher::new(&uploader, dir.clone(), &[], None)?;
+        let excluded = (
+            watcher.is_manifest(&dir.join(DEFAULT_MANIFEST_FILE)),
+            watcher.is_manifest(&dir.join("build.log")),
+        );
+        fs::remove_dir_all(&dir)?;
+        assert_eq!((true, false), excluded);
+        Ok(())
+    }
+
+    #[test]
+    fn all_files_are_included_without_patterns() {
+        let config = Config::default();
+        let uploader = Uploader::new(&config);
+        let watcher = Watcher::new(&uploader, PathBuf::from("artifacts"), &[], None)
+            .expect("watcher should be created");
+
+        assert!(watcher.is_included("anything"));
+    }
+}